@@ -0,0 +1,290 @@
+//! Animating values over time, used for smooth scrolling.
+//!
+//! There are two kinds of motion: An [`Animation`][Animation] moves a value to a known target over
+//! a fixed duration, e.g. when scrolling the cursor into view. [`Momentum`][Momentum] keeps a value
+//! moving at a velocity that slowly dies down, e.g. after flinging a trackpad.
+//!
+//! Neither of these does anything on its own. The main loop samples them each frame and keeps
+//! waking itself up to draw another frame for as long as any of them are still moving.
+//!
+//! [Animation]: struct.Animation.html
+//! [Momentum]: struct.Momentum.html
+
+use std::time::{Duration, Instant};
+
+/// The fraction of momentum lost per second is `1 - e^-MOMENTUM_DECAY`.
+const MOMENTUM_DECAY: f32 = 4.0;
+
+/// Momentum slower than this, in units per second, is considered to have stopped.
+const MIN_MOMENTUM_VELOCITY: f32 = 10.0;
+
+/// If there's a gap this long between two samples tracked by a [`VelocityTracker`][VelocityTracker]
+/// then the motion is considered to have stopped in between.
+///
+/// [VelocityTracker]: struct.VelocityTracker.html
+const VELOCITY_TIMEOUT_MS: u64 = 100;
+
+/// How an animation speeds up and slows down over its duration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    /// Moves at a constant speed.
+    Linear,
+
+    /// Starts quickly and slows down towards the end.
+    EaseOut,
+
+    /// Starts slowly, speeds up, and slows down again towards the end.
+    EaseInOut,
+}
+
+impl Easing {
+    /// Looks up an easing by the name used for it in the config file, e.g. "ease_out".
+    pub fn from_name(name: &str) -> Option<Easing> {
+        match name {
+            "linear" => Some(Easing::Linear),
+            "ease_out" => Some(Easing::EaseOut),
+            "ease_in_out" => Some(Easing::EaseInOut),
+            _ => None,
+        }
+    }
+
+    /// Maps the fraction of the animation's duration that has passed to the fraction of the
+    /// distance that should have been covered. Both are between 0 and 1.
+    pub fn apply(&self, t: f32) -> f32 {
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t.powi(3)
+                } else {
+                    1.0 - (2.0 - 2.0 * t).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
+/// Moves a value from one point to another over a fixed duration.
+#[derive(Debug, Clone, Copy)]
+pub struct Animation {
+    from: f32,
+    to: f32,
+    start: Instant,
+    duration: Duration,
+    easing: Easing,
+}
+
+impl Animation {
+    /// Starts an animation at `now`.
+    pub fn new(from: f32, to: f32, duration: Duration, easing: Easing, now: Instant) -> Animation {
+        Animation {
+            from: from,
+            to: to,
+            start: now,
+            duration: duration,
+            easing: easing,
+        }
+    }
+
+    /// Returns the value the animation is moving towards.
+    pub fn target(&self) -> f32 {
+        self.to
+    }
+
+    /// Returns the value of the animation at `now`.
+    pub fn value_at(&self, now: Instant) -> f32 {
+        let progress = if self.is_finished(now) {
+            1.0
+        } else {
+            seconds(elapsed(self.start, now)) / seconds(self.duration)
+        };
+
+        self.from + (self.to - self.from) * self.easing.apply(progress)
+    }
+
+    /// Returns `true` if the animation has reached its target by `now`.
+    pub fn is_finished(&self, now: Instant) -> bool {
+        elapsed(self.start, now) >= self.duration
+    }
+}
+
+/// Motion that continues at a velocity that decays over time.
+#[derive(Debug, Clone, Copy)]
+pub struct Momentum {
+    /// The current velocity, in units per second.
+    velocity: f32,
+
+    /// When the motion was last advanced.
+    last_step: Instant,
+}
+
+impl Momentum {
+    /// Starts moving at `velocity` units per second.
+    pub fn new(velocity: f32, now: Instant) -> Momentum {
+        Momentum {
+            velocity: velocity,
+            last_step: now,
+        }
+    }
+
+    /// Advances the motion to `now`, returning how far it moved since the last step.
+    pub fn step(&mut self, now: Instant) -> f32 {
+        let delta_time = seconds(elapsed(self.last_step, now));
+        self.last_step = now;
+
+        let distance = self.velocity * delta_time;
+        self.velocity *= (-MOMENTUM_DECAY * delta_time).exp();
+        distance
+    }
+
+    /// Returns `true` once the motion has slowed down enough to stop.
+    pub fn is_stopped(&self) -> bool {
+        self.velocity.abs() < MIN_MOMENTUM_VELOCITY
+    }
+}
+
+/// Estimates how fast something is moving from a series of movements, e.g. trackpad scroll events.
+#[derive(Debug, Clone, Copy)]
+pub struct VelocityTracker {
+    /// The estimated velocity, in units per second.
+    velocity: f32,
+
+    /// When the last movement happened.
+    last_sample: Option<Instant>,
+}
+
+impl VelocityTracker {
+    pub fn new() -> VelocityTracker {
+        VelocityTracker {
+            velocity: 0.0,
+            last_sample: None,
+        }
+    }
+
+    /// Records a movement of `distance` at `now`.
+    pub fn add_sample(&mut self, distance: f32, now: Instant) {
+        let delta_time = match self.last_sample {
+            Some(last_sample) if elapsed(last_sample, now) < Duration::from_millis(VELOCITY_TIMEOUT_MS) => {
+                Some(seconds(elapsed(last_sample, now)))
+            }
+            _ => None,
+        };
+        self.last_sample = Some(now);
+
+        match delta_time {
+            // Events can arrive in bursts with almost no time between them, so the velocity is only
+            // updated when there's a meaningful gap, and each new measurement is averaged with the
+            // previous ones to smooth out noise.
+            Some(delta_time) if delta_time > 0.001 => {
+                self.velocity = 0.6 * (distance / delta_time) + 0.4 * self.velocity;
+            }
+            Some(_) => {}
+
+            // Without a recent movement to compare against there's nothing to measure, so the
+            // motion starts over from rest.
+            None => self.velocity = 0.0,
+        }
+    }
+
+    /// Returns the estimated velocity at `now`, which is zero if there haven't been any recent
+    /// movements.
+    pub fn velocity(&self, now: Instant) -> f32 {
+        match self.last_sample {
+            Some(last_sample) if elapsed(last_sample, now) < Duration::from_millis(VELOCITY_TIMEOUT_MS) => self.velocity,
+            _ => 0.0,
+        }
+    }
+
+    /// Forgets all previous movements.
+    pub fn reset(&mut self) {
+        *self = VelocityTracker::new();
+    }
+}
+
+/// Returns the time from `earlier` to `later`, or zero if `later` is actually earlier.
+fn elapsed(earlier: Instant, later: Instant) -> Duration {
+    if later > earlier {
+        later.duration_since(earlier)
+    } else {
+        Duration::from_millis(0)
+    }
+}
+
+/// Converts a duration to fractional seconds.
+fn seconds(duration: Duration) -> f32 {
+    duration.as_secs() as f32 + duration.subsec_nanos() as f32 / 1_000_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn easing_starts_and_ends_at_endpoints() {
+        for &easing in &[Easing::Linear, Easing::EaseOut, Easing::EaseInOut] {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert_eq!(easing.apply(1.0), 1.0);
+        }
+
+        assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+    }
+
+    #[test]
+    fn animation_reaches_target_at_duration() {
+        let start = Instant::now();
+        let animation = Animation::new(100.0, 200.0, Duration::from_millis(200), Easing::Linear, start);
+
+        assert_eq!(animation.value_at(start), 100.0);
+        assert_eq!(animation.value_at(start + Duration::from_millis(100)), 150.0);
+        assert!(!animation.is_finished(start + Duration::from_millis(199)));
+
+        assert!(animation.is_finished(start + Duration::from_millis(200)));
+        assert_eq!(animation.value_at(start + Duration::from_millis(200)), 200.0);
+        assert_eq!(animation.value_at(start + Duration::from_millis(500)), 200.0);
+    }
+
+    #[test]
+    fn momentum_decays_until_stopped() {
+        let start = Instant::now();
+        let mut momentum = Momentum::new(1000.0, start);
+        assert!(!momentum.is_stopped());
+
+        // The first step moves at the starting velocity, after which it slows down.
+        let first = momentum.step(start + Duration::from_millis(100));
+        assert!((first - 100.0).abs() < 0.01);
+        let second = momentum.step(start + Duration::from_millis(200));
+        assert!(second > 0.0 && second < first);
+
+        momentum.step(start + Duration::from_millis(2000));
+        assert!(momentum.is_stopped());
+    }
+
+    #[test]
+    fn velocity_is_measured_between_samples() {
+        let start = Instant::now();
+        let mut tracker = VelocityTracker::new();
+        tracker.add_sample(10.0, start);
+        assert_eq!(tracker.velocity(start), 0.0);
+
+        let next = start + Duration::from_millis(10);
+        tracker.add_sample(10.0, next);
+        assert!((tracker.velocity(next) - 600.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn velocity_resets_after_timeout() {
+        let start = Instant::now();
+        let mut tracker = VelocityTracker::new();
+        tracker.add_sample(10.0, start);
+        tracker.add_sample(10.0, start + Duration::from_millis(10));
+
+        let late = start + Duration::from_millis(10 + VELOCITY_TIMEOUT_MS);
+        assert_eq!(tracker.velocity(late), 0.0);
+
+        // A movement after a pause starts over from rest rather than picking up the old velocity.
+        tracker.add_sample(10.0, late);
+        assert_eq!(tracker.velocity(late), 0.0);
+    }
+}
@@ -0,0 +1,276 @@
+//! Reading and writing the system clipboard.
+//!
+//! On Linux there are two clipboards: the regular clipboard used by copy and paste, and the
+//! primary selection, which holds whatever text was last selected and is pasted with the middle
+//! mouse button. Both are represented by [`Selection`][Selection].
+//!
+//! Rather than talking to the display server directly, [`SystemClipboard`][SystemClipboard] runs
+//! the standard command line tools for it: `xclip` on X11, and `wl-copy`/`wl-paste` (from
+//! wl-clipboard) on Wayland. These aren't installed everywhere, so they need to be installed
+//! separately for the editor to share the clipboard with other applications.
+//! [`MemoryClipboard`][MemoryClipboard] keeps everything in memory instead, which is used when
+//! there's no display server to talk to or the tools aren't installed, and is handy for testing.
+//!
+//! [Selection]: enum.Selection.html
+//! [SystemClipboard]: struct.SystemClipboard.html
+//! [MemoryClipboard]: struct.MemoryClipboard.html
+
+use std::env;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Which of the clipboards to use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    /// The clipboard used by copy, cut, and paste.
+    Clipboard,
+
+    /// The most recently selected text, pasted with the middle mouse button.
+    Primary,
+}
+
+/// Somewhere to copy text to and paste text from.
+pub trait Clipboard {
+    /// Returns the text in a selection, or `None` if it's empty or couldn't be read.
+    fn get(&mut self, selection: Selection) -> Option<String>;
+
+    /// Replaces the contents of a selection with `text`.
+    fn set(&mut self, selection: Selection, text: String);
+}
+
+/// Returns the clipboard for the current display server, or an in-memory clipboard if there isn't
+/// one or its tools aren't installed.
+pub fn system_clipboard() -> Box<Clipboard> {
+    match SystemClipboard::new() {
+        Some(ref clipboard) if !clipboard.tools_installed() => {
+            eprintln!(
+                "{} isn't installed, copied text will only be available within the editor",
+                clipboard.tools().join(" or ")
+            );
+            Box::new(MemoryClipboard::new())
+        }
+        Some(clipboard) => Box::new(clipboard),
+        None => {
+            eprintln!("No display server found, copied text will only be available within the editor");
+            Box::new(MemoryClipboard::new())
+        }
+    }
+}
+
+/// A clipboard that only exists within the editor.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryClipboard {
+    clipboard: Option<String>,
+    primary: Option<String>,
+}
+
+impl MemoryClipboard {
+    pub fn new() -> MemoryClipboard {
+        MemoryClipboard::default()
+    }
+}
+
+impl Clipboard for MemoryClipboard {
+    fn get(&mut self, selection: Selection) -> Option<String> {
+        match selection {
+            Selection::Clipboard => self.clipboard.clone(),
+            Selection::Primary => self.primary.clone(),
+        }
+    }
+
+    fn set(&mut self, selection: Selection, text: String) {
+        match selection {
+            Selection::Clipboard => self.clipboard = Some(text),
+            Selection::Primary => self.primary = Some(text),
+        }
+    }
+}
+
+/// The display servers the system clipboard knows how to talk to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DisplayServer {
+    X11,
+    Wayland,
+}
+
+/// The clipboard shared with other applications.
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClipboard {
+    display_server: DisplayServer,
+}
+
+impl SystemClipboard {
+    /// Creates a clipboard for the display server the editor is running under, or returns `None`
+    /// if it isn't running under one we support.
+    pub fn new() -> Option<SystemClipboard> {
+        // XWayland sets `DISPLAY` as well, so check for Wayland first.
+        let display_server = if env::var_os("WAYLAND_DISPLAY").is_some() {
+            DisplayServer::Wayland
+        } else if env::var_os("DISPLAY").is_some() {
+            DisplayServer::X11
+        } else {
+            return None;
+        };
+
+        Some(SystemClipboard { display_server: display_server })
+    }
+
+    /// Returns the names of the command line tools the clipboard runs.
+    fn tools(&self) -> &'static [&'static str] {
+        match self.display_server {
+            DisplayServer::X11 => &["xclip"],
+            DisplayServer::Wayland => &["wl-copy", "wl-paste"],
+        }
+    }
+
+    /// Returns `true` if all of the clipboard's tools can be run, by asking each for its version.
+    fn tools_installed(&self) -> bool {
+        let version_flag = match self.display_server {
+            DisplayServer::X11 => "-version",
+            DisplayServer::Wayland => "--version",
+        };
+
+        self.tools().iter().all(|tool| {
+            Command::new(tool)
+                .arg(version_flag)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok()
+        })
+    }
+
+    /// Builds the command that writes the contents of a selection to its stdout.
+    fn paste_command(&self, selection: Selection) -> Command {
+        match self.display_server {
+            DisplayServer::X11 => {
+                let mut command = Command::new("xclip");
+                command.arg("-selection").arg(xclip_selection(selection)).arg("-out");
+                command
+            }
+            DisplayServer::Wayland => {
+                let mut command = Command::new("wl-paste");
+                command.arg("--no-newline");
+                if selection == Selection::Primary {
+                    command.arg("--primary");
+                }
+                command
+            }
+        }
+    }
+
+    /// Builds the command that replaces the contents of a selection with its stdin.
+    fn copy_command(&self, selection: Selection) -> Command {
+        match self.display_server {
+            DisplayServer::X11 => {
+                let mut command = Command::new("xclip");
+                command.arg("-selection").arg(xclip_selection(selection)).arg("-in");
+                command
+            }
+            DisplayServer::Wayland => {
+                let mut command = Command::new("wl-copy");
+                if selection == Selection::Primary {
+                    command.arg("--primary");
+                }
+                command
+            }
+        }
+    }
+}
+
+impl Clipboard for SystemClipboard {
+    fn get(&mut self, selection: Selection) -> Option<String> {
+        let output = self.paste_command(selection)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+
+        match output {
+            // The tools fail when the selection is empty, which isn't worth reporting.
+            Ok(output) => {
+                if output.status.success() {
+                    String::from_utf8(output.stdout).ok()
+                } else {
+                    None
+                }
+            }
+
+            Err(error) => {
+                eprintln!("Failed to read from the clipboard: {}", error);
+                None
+            }
+        }
+    }
+
+    fn set(&mut self, selection: Selection, text: String) {
+        if let Err(error) = run_with_input(self.copy_command(selection), &*text) {
+            eprintln!("Failed to write to the clipboard: {}", error);
+        }
+    }
+}
+
+/// Returns the name `xclip` uses for a selection.
+fn xclip_selection(selection: Selection) -> &'static str {
+    match selection {
+        Selection::Clipboard => "clipboard",
+        Selection::Primary => "primary",
+    }
+}
+
+/// Runs a command with `input` written to its stdin.
+///
+/// The copy tools fork into the background to keep serving the selection to other applications,
+/// so this only waits until the input has been read.
+fn run_with_input(mut command: Command, input: &str) -> io::Result<()> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    // Close stdin once the input is written so that the tool knows there's no more coming.
+    {
+        let stdin = child.stdin.as_mut().expect("No stdin pipe to clipboard process");
+        stdin.write_all(input.as_bytes())?;
+    }
+    child.stdin = None;
+
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::Other, format!("clipboard command exited with {}", status)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_clipboard_starts_empty() {
+        let mut clipboard = MemoryClipboard::new();
+        assert_eq!(clipboard.get(Selection::Clipboard), None);
+        assert_eq!(clipboard.get(Selection::Primary), None);
+    }
+
+    #[test]
+    fn memory_clipboard_keeps_selections_separate() {
+        let mut clipboard = MemoryClipboard::new();
+        clipboard.set(Selection::Clipboard, "copied".into());
+        clipboard.set(Selection::Primary, "selected".into());
+
+        assert_eq!(clipboard.get(Selection::Clipboard), Some("copied".into()));
+        assert_eq!(clipboard.get(Selection::Primary), Some("selected".into()));
+    }
+
+    #[test]
+    fn memory_clipboard_replaces_contents() {
+        let mut clipboard = MemoryClipboard::new();
+        clipboard.set(Selection::Clipboard, "first".into());
+        clipboard.set(Selection::Clipboard, "second".into());
+
+        assert_eq!(clipboard.get(Selection::Clipboard), Some("second".into()));
+    }
+}
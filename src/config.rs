@@ -0,0 +1,328 @@
+//! Settings that control the appearance and behavior of the editor.
+//!
+//! The defaults can be overridden by a JSON config file, by default
+//! `$XDG_CONFIG_HOME/text-edit/config.json` (or `~/.config/text-edit/config.json`). Anything the
+//! file doesn't mention keeps its default value, e.g.:
+//!
+//! ```json
+//! {
+//!     "scroll_duration_ms": 200,
+//!     "scroll_easing": "ease_in_out",
+//!     "relative_line_numbers": true,
+//!     "selection_color": "#264f78",
+//!     "find_match_color": "#613214",
+//!     "current_line_color": "#282828",
+//!     "cursor_shape": "block",
+//!     "cursor_color": "#e0a030",
+//!     "cursor_width": 2,
+//!     "cursor_blink_ms": 0,
+//!     "keymap": {
+//!         "ctrl+k ctrl+w": "close_tab",
+//!         "ctrl+w": null
+//!     }
+//! }
+//! ```
+//!
+//! Colors are written as `#rrggbb`, or `#rrggbbaa` to include an alpha channel.
+//!
+//! Each entry in `keymap` binds a key sequence (see the [`keymap`][keymap] module for the format)
+//! to the name of a command, or removes the default binding if the command is `null`.
+//!
+//! [keymap]: ../keymap/index.html
+
+use animation::Easing;
+use cursor::{CursorShape, CursorStyle};
+use keymap::{self, Command, Keymap};
+use serde_json;
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use webrender_traits::ColorF;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// The background color used to highlight selected text, set by `selection_color` in the
+    /// config file.
+    pub selection_color: ColorF,
+
+    /// The background color used to highlight matches of the current search, set by
+    /// `find_match_color` in the config file.
+    pub find_match_color: ColorF,
+
+    /// The background color used to highlight lines containing a cursor, set by
+    /// `current_line_color` in the config file.
+    pub current_line_color: ColorF,
+
+    /// The shape, color, and thickness of cursors, set by `cursor_shape`, `cursor_color`, and
+    /// `cursor_width` in the config file.
+    pub cursor_style: CursorStyle,
+
+    /// How long a blinking cursor stays shown or hidden. Zero disables blinking.
+    pub cursor_blink_interval: Duration,
+
+    /// How long smooth scrolling takes to reach its destination. Zero disables smooth scrolling.
+    pub scroll_duration: Duration,
+
+    /// How smooth scrolling speeds up and slows down.
+    pub scroll_easing: Easing,
+
+    /// Whether the gutter numbers lines by their distance from the cursor rather than from the
+    /// start of the document.
+    pub relative_line_numbers: bool,
+
+    /// The key bindings for editor commands.
+    pub keymap: Keymap,
+}
+
+impl Config {
+    /// Loads the config file at `path`, using the default settings for anything it doesn't set.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, ConfigError> {
+        let file = File::open(path)?;
+        let config_file: ConfigFile = serde_json::from_reader(file)?;
+
+        let mut config = Config::default();
+
+        if let Some(scroll_duration_ms) = config_file.scroll_duration_ms {
+            config.scroll_duration = Duration::from_millis(scroll_duration_ms);
+        }
+
+        if let Some(scroll_easing) = config_file.scroll_easing {
+            config.scroll_easing = match Easing::from_name(&*scroll_easing) {
+                Some(easing) => easing,
+                None => return Err(ConfigError::InvalidValue("scroll_easing", scroll_easing)),
+            };
+        }
+
+        if let Some(selection_color) = config_file.selection_color {
+            config.selection_color = match parse_color(&*selection_color) {
+                Some(color) => color,
+                None => return Err(ConfigError::InvalidValue("selection_color", selection_color)),
+            };
+        }
+
+        if let Some(find_match_color) = config_file.find_match_color {
+            config.find_match_color = match parse_color(&*find_match_color) {
+                Some(color) => color,
+                None => return Err(ConfigError::InvalidValue("find_match_color", find_match_color)),
+            };
+        }
+
+        if let Some(current_line_color) = config_file.current_line_color {
+            config.current_line_color = match parse_color(&*current_line_color) {
+                Some(color) => color,
+                None => return Err(ConfigError::InvalidValue("current_line_color", current_line_color)),
+            };
+        }
+
+        if let Some(cursor_shape) = config_file.cursor_shape {
+            config.cursor_style.shape = match CursorShape::from_name(&*cursor_shape) {
+                Some(shape) => shape,
+                None => return Err(ConfigError::InvalidValue("cursor_shape", cursor_shape)),
+            };
+        }
+
+        if let Some(cursor_color) = config_file.cursor_color {
+            config.cursor_style.color = match parse_color(&*cursor_color) {
+                Some(color) => color,
+                None => return Err(ConfigError::InvalidValue("cursor_color", cursor_color)),
+            };
+        }
+
+        if let Some(cursor_width) = config_file.cursor_width {
+            if !(cursor_width > 0.0) {
+                return Err(ConfigError::InvalidValue("cursor_width", cursor_width.to_string()));
+            }
+            config.cursor_style.width = cursor_width;
+        }
+
+        if let Some(cursor_blink_ms) = config_file.cursor_blink_ms {
+            config.cursor_blink_interval = Duration::from_millis(cursor_blink_ms);
+        }
+
+        if let Some(relative_line_numbers) = config_file.relative_line_numbers {
+            config.relative_line_numbers = relative_line_numbers;
+        }
+
+        if let Some(bindings) = config_file.keymap {
+            for (keys, command) in bindings {
+                let chords = match keymap::parse_keys(&*keys) {
+                    Some(chords) => chords,
+                    None => return Err(ConfigError::InvalidKeys(keys)),
+                };
+
+                match command {
+                    Some(name) => {
+                        let command = match Command::from_name(&*name) {
+                            Some(command) => command,
+                            None => return Err(ConfigError::UnknownCommand(name)),
+                        };
+                        config.keymap.bind(chords, command);
+                    }
+                    None => config.keymap.unbind(&*chords),
+                }
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Returns the default location of the config file, if there's a home or config directory to
+    /// look in.
+    pub fn default_path() -> Option<PathBuf> {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::home_dir().map(|home_dir| home_dir.join(".config")))
+            .map(|config_dir| config_dir.join("text-edit").join("config.json"))
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            selection_color: ColorF::new(0.2, 0.3, 0.45, 1.0),
+            find_match_color: ColorF::new(0.45, 0.35, 0.1, 1.0),
+            current_line_color: ColorF::new(0.13, 0.13, 0.13, 1.0),
+            cursor_style: CursorStyle::default(),
+            cursor_blink_interval: Duration::from_millis(530),
+            scroll_duration: Duration::from_millis(150),
+            scroll_easing: Easing::EaseOut,
+            relative_line_numbers: false,
+            keymap: Keymap::default(),
+        }
+    }
+}
+
+/// An error that occurred while loading the config file.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file couldn't be read.
+    Io(io::Error),
+
+    /// The file isn't valid JSON, or a setting has the wrong type.
+    Json(serde_json::Error),
+
+    /// A setting has a value that isn't one of the ones it accepts.
+    InvalidValue(&'static str, String),
+
+    /// A key sequence in the keymap couldn't be parsed.
+    InvalidKeys(String),
+
+    /// The keymap binds a key sequence to a command that doesn't exist.
+    UnknownCommand(String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::Io(ref error) => write!(formatter, "Couldn't read config file: {}", error),
+            ConfigError::Json(ref error) => write!(formatter, "Invalid config file: {}", error),
+            ConfigError::InvalidValue(setting, ref value) => {
+                write!(formatter, "Invalid value {:?} for {:?}", value, setting)
+            }
+            ConfigError::InvalidKeys(ref keys) => write!(formatter, "Invalid key sequence {:?}", keys),
+            ConfigError::UnknownCommand(ref command) => write!(formatter, "Unknown command {:?}", command),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn description(&self) -> &str {
+        match *self {
+            ConfigError::Io(_) => "couldn't read config file",
+            ConfigError::Json(_) => "invalid config file",
+            ConfigError::InvalidValue(..) => "invalid value for setting",
+            ConfigError::InvalidKeys(_) => "invalid key sequence",
+            ConfigError::UnknownCommand(_) => "unknown command",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            ConfigError::Io(ref error) => Some(error),
+            ConfigError::Json(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(error: io::Error) -> ConfigError {
+        ConfigError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for ConfigError {
+    fn from(error: serde_json::Error) -> ConfigError {
+        ConfigError::Json(error)
+    }
+}
+
+/// The contents of the config file, where every setting is optional.
+#[derive(Debug, Deserialize)]
+struct ConfigFile {
+    scroll_duration_ms: Option<u64>,
+    scroll_easing: Option<String>,
+    relative_line_numbers: Option<bool>,
+    selection_color: Option<String>,
+    find_match_color: Option<String>,
+    current_line_color: Option<String>,
+    cursor_shape: Option<String>,
+    cursor_color: Option<String>,
+    cursor_width: Option<f32>,
+    cursor_blink_ms: Option<u64>,
+
+    /// Key sequences mapped to command names, or `None` to remove a binding.
+    keymap: Option<HashMap<String, Option<String>>>,
+}
+
+/// Parses a color written as `#rrggbb` or `#rrggbbaa`.
+fn parse_color(text: &str) -> Option<ColorF> {
+    if !text.starts_with('#') {
+        return None;
+    }
+
+    let digits = &text[1..];
+    if !(digits.len() == 6 || digits.len() == 8) || !digits.chars().all(|c| c.is_digit(16)) {
+        return None;
+    }
+
+    // Every digit is ASCII, so the string can be sliced on any byte.
+    let components: Vec<f32> = (0..digits.len() / 2)
+        .map(|index| u8::from_str_radix(&digits[index * 2..index * 2 + 2], 16).unwrap() as f32 / 255.0)
+        .collect();
+
+    let alpha = components.get(3).cloned().unwrap_or(1.0);
+    Some(ColorF::new(components[0], components[1], components[2], alpha))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_color_rgb() {
+        assert_eq!(parse_color("#ff0000"), Some(ColorF::new(1.0, 0.0, 0.0, 1.0)));
+        assert_eq!(parse_color("#00FF00"), Some(ColorF::new(0.0, 1.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn parse_color_rgba() {
+        assert_eq!(parse_color("#0000ff00"), Some(ColorF::new(0.0, 0.0, 1.0, 0.0)));
+        assert_eq!(parse_color("#ffffffff"), Some(ColorF::new(1.0, 1.0, 1.0, 1.0)));
+    }
+
+    #[test]
+    fn parse_color_invalid() {
+        assert_eq!(parse_color(""), None);
+        assert_eq!(parse_color("ff0000"), None);
+        assert_eq!(parse_color("#ff00"), None);
+        assert_eq!(parse_color("#ff00000"), None);
+        assert_eq!(parse_color("#gg0000"), None);
+        assert_eq!(parse_color("#ff00\u{e9}"), None);
+    }
+}
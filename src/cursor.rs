@@ -0,0 +1,241 @@
+//! Drawing cursors.
+//!
+//! A cursor is drawn relative to the cell of the character just after it: as a bar along the
+//! cell's left edge, a block filling the cell, or an underline along the cell's bottom edge. While
+//! the cursor doesn't have keyboard focus it's drawn as a hollow block regardless of its shape, so
+//! that it's clear typing won't go there.
+//!
+//! A block cursor hides the character it covers, so the character is drawn again on top of it in
+//! [`CursorStyle::text_color()`][text_color].
+//!
+//! A focused cursor can also blink, see [`Blink`][Blink].
+//!
+//! [Blink]: struct.Blink.html
+//! [text_color]: struct.CursorStyle.html#method.text_color
+
+use std::time::{Duration, Instant};
+use webrender_traits::{BorderRadius, BorderSide, BorderStyle, ClipRegion, ColorF, DisplayListBuilder};
+use webrender_traits::{LayoutPoint, LayoutRect, LayoutSize};
+
+/// The shape of a cursor while it has focus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorShape {
+    Bar,
+    Block,
+    Underline,
+}
+
+impl CursorShape {
+    /// Looks up a shape by the name used for it in the config file, e.g. "block".
+    pub fn from_name(name: &str) -> Option<CursorShape> {
+        match name {
+            "bar" => Some(CursorShape::Bar),
+            "block" => Some(CursorShape::Block),
+            "underline" => Some(CursorShape::Underline),
+            _ => None,
+        }
+    }
+}
+
+/// How cursors are drawn.
+#[derive(Debug, Clone, Copy)]
+pub struct CursorStyle {
+    pub shape: CursorShape,
+    pub color: ColorF,
+
+    /// The thickness of a bar or underline cursor, in pixels.
+    pub width: f32,
+}
+
+impl CursorStyle {
+    /// Returns `true` if the cursor hides the character it's in front of.
+    pub fn covers_text(&self, focused: bool) -> bool {
+        focused && self.shape == CursorShape::Block
+    }
+
+    /// Returns the color to draw the character covered by a block cursor in: black on a light
+    /// cursor and white on a dark one.
+    pub fn text_color(&self) -> ColorF {
+        let luminance = 0.299 * self.color.r + 0.587 * self.color.g + 0.114 * self.color.b;
+        if luminance > 0.5 {
+            ColorF::new(0.0, 0.0, 0.0, 1.0)
+        } else {
+            ColorF::new(1.0, 1.0, 1.0, 1.0)
+        }
+    }
+}
+
+impl Default for CursorStyle {
+    fn default() -> CursorStyle {
+        CursorStyle {
+            shape: CursorShape::Bar,
+            color: ColorF::new(1.0, 1.0, 1.0, 1.0),
+            width: 1.0,
+        }
+    }
+}
+
+/// Draws a cursor in front of the character occupying `cell`.
+///
+/// This should be drawn before the text, so that bar and underline cursors don't hide parts of the
+/// character. If [`covers_text()`][covers_text] is `true`, the character needs to be drawn again
+/// afterwards in [`text_color()`][text_color].
+///
+/// [covers_text]: struct.CursorStyle.html#method.covers_text
+/// [text_color]: struct.CursorStyle.html#method.text_color
+pub fn push_cursor(
+    builder: &mut DisplayListBuilder,
+    clip_region: ClipRegion,
+    cell: LayoutRect,
+    style: &CursorStyle,
+    focused: bool,
+) {
+    if !focused {
+        let side = BorderSide {
+            width: 1.0,
+            color: style.color,
+            style: BorderStyle::Solid,
+        };
+        builder.push_border(cell, clip_region, side, side, side, side, BorderRadius::uniform(0.0));
+        return;
+    }
+
+    let rect = match style.shape {
+        CursorShape::Bar => LayoutRect::new(cell.origin, LayoutSize::new(style.width, cell.size.height)),
+        CursorShape::Block => cell,
+        CursorShape::Underline => {
+            LayoutRect::new(
+                LayoutPoint::new(cell.origin.x, cell.origin.y + cell.size.height - style.width),
+                LayoutSize::new(cell.size.width, style.width),
+            )
+        }
+    };
+    builder.push_rect(rect, clip_region, style.color);
+}
+
+/// Keeps track of whether a blinking cursor is currently shown.
+///
+/// The cursor starts out shown and then alternates between hidden and shown every `interval`.
+/// Typing restarts the blink so that the cursor is always visible while it's moving.
+#[derive(Debug, Clone, Copy)]
+pub struct Blink {
+    /// How long the cursor stays shown or hidden, or zero if it doesn't blink.
+    interval_ms: u64,
+
+    /// When the cursor was last shown.
+    start: Instant,
+}
+
+impl Blink {
+    pub fn new(interval: Duration, now: Instant) -> Blink {
+        Blink {
+            interval_ms: interval.as_secs() * 1000 + interval.subsec_nanos() as u64 / 1_000_000,
+            start: now,
+        }
+    }
+
+    /// Shows the cursor and starts blinking again from `now`.
+    pub fn reset(&mut self, now: Instant) {
+        self.start = now;
+    }
+
+    /// Returns `true` if the cursor should be shown at `now`.
+    pub fn is_visible(&self, now: Instant) -> bool {
+        match self.phase(now) {
+            Some(phase) => phase % 2 == 0,
+            None => true,
+        }
+    }
+
+    /// Returns when the cursor next changes between shown and hidden after `now`, or `None` if it
+    /// doesn't blink.
+    pub fn next_change(&self, now: Instant) -> Option<Instant> {
+        self.phase(now).map(|phase| self.start + Duration::from_millis(self.interval_ms * (phase + 1)))
+    }
+
+    /// Returns how many times the cursor has been shown or hidden by `now`.
+    fn phase(&self, now: Instant) -> Option<u64> {
+        if self.interval_ms == 0 {
+            return None;
+        }
+
+        let elapsed = if now > self.start {
+            now.duration_since(self.start)
+        } else {
+            Duration::from_millis(0)
+        };
+        let elapsed_ms = elapsed.as_secs() * 1000 + elapsed.subsec_nanos() as u64 / 1_000_000;
+        Some(elapsed_ms / self.interval_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn text_color_contrasts_with_cursor() {
+        let mut style = CursorStyle::default();
+        assert_eq!(style.text_color(), ColorF::new(0.0, 0.0, 0.0, 1.0));
+
+        style.color = ColorF::new(0.1, 0.1, 0.4, 1.0);
+        assert_eq!(style.text_color(), ColorF::new(1.0, 1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn only_focused_block_cursor_covers_text() {
+        let mut style = CursorStyle::default();
+        assert!(!style.covers_text(true));
+
+        style.shape = CursorShape::Block;
+        assert!(style.covers_text(true));
+        assert!(!style.covers_text(false));
+    }
+
+    #[test]
+    fn blink_alternates_every_interval() {
+        let start = Instant::now();
+        let blink = Blink::new(Duration::from_millis(500), start);
+
+        assert!(blink.is_visible(start));
+        assert!(blink.is_visible(start + Duration::from_millis(499)));
+        assert!(!blink.is_visible(start + Duration::from_millis(500)));
+        assert!(!blink.is_visible(start + Duration::from_millis(999)));
+        assert!(blink.is_visible(start + Duration::from_millis(1000)));
+    }
+
+    #[test]
+    fn blink_next_change() {
+        let start = Instant::now();
+        let blink = Blink::new(Duration::from_millis(500), start);
+
+        assert_eq!(blink.next_change(start), Some(start + Duration::from_millis(500)));
+        assert_eq!(
+            blink.next_change(start + Duration::from_millis(750)),
+            Some(start + Duration::from_millis(1000))
+        );
+    }
+
+    #[test]
+    fn blink_reset_shows_cursor() {
+        let start = Instant::now();
+        let mut blink = Blink::new(Duration::from_millis(500), start);
+
+        let hidden = start + Duration::from_millis(600);
+        assert!(!blink.is_visible(hidden));
+
+        blink.reset(hidden);
+        assert!(blink.is_visible(hidden));
+        assert_eq!(blink.next_change(hidden), Some(hidden + Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn blink_disabled() {
+        let start = Instant::now();
+        let blink = Blink::new(Duration::from_millis(0), start);
+
+        assert!(blink.is_visible(start + Duration::from_millis(12345)));
+        assert_eq!(blink.next_change(start), None);
+    }
+}
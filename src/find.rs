@@ -0,0 +1,103 @@
+//! The find and replace panel shown along the bottom of the window.
+//!
+//! The panel has a field for the text to search for and, when replacing, a field for the text to
+//! replace matches with. While the panel is open it takes all keyboard input:
+//!
+//! - Enter selects the next match and Shift+Enter the previous one. In the replace field, Enter
+//!   replaces the selected match instead.
+//! - Tab switches between the two fields.
+//! - Alt+C, Alt+R, and Alt+W toggle case-sensitive, regular expression, and whole word matching.
+//! - Alt+A replaces every match.
+//! - Escape closes the panel.
+//!
+//! The searching itself is done by xi-core. The main loop sends a new search to the focused tab
+//! whenever the query or options change, and xi-core highlights the matches in the lines it sends
+//! back.
+
+/// The text fields in the panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindField {
+    Query,
+    Replacement,
+}
+
+/// The state of the find panel.
+///
+/// This sticks around while the panel is closed so that reopening it, or moving to the next match
+/// with the panel closed, picks up the last search.
+#[derive(Debug, Clone)]
+pub struct FindPanel {
+    pub is_open: bool,
+
+    /// Whether the replacement field is shown.
+    pub is_replacing: bool,
+
+    /// The field that typing goes into.
+    pub focused_field: FindField,
+
+    /// The text to search for.
+    pub query: String,
+
+    /// The text to replace matches with.
+    pub replacement: String,
+
+    pub case_sensitive: bool,
+
+    /// Whether the query is a regular expression rather than plain text.
+    pub regex: bool,
+
+    /// Whether to only match whole words.
+    pub whole_words: bool,
+}
+
+impl FindPanel {
+    pub fn new() -> FindPanel {
+        FindPanel {
+            is_open: false,
+            is_replacing: false,
+            focused_field: FindField::Query,
+            query: String::new(),
+            replacement: String::new(),
+            case_sensitive: false,
+            regex: false,
+            whole_words: false,
+        }
+    }
+
+    /// Opens the panel with the query field focused, showing the replacement field if
+    /// `replacing` is `true`.
+    pub fn open(&mut self, replacing: bool) {
+        self.is_open = true;
+        self.is_replacing = replacing;
+        self.focused_field = FindField::Query;
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+    }
+
+    /// Moves focus to the other field, if the replacement field is shown.
+    pub fn switch_field(&mut self) {
+        self.focused_field = match self.focused_field {
+            FindField::Query if self.is_replacing => FindField::Replacement,
+            _ => FindField::Query,
+        };
+    }
+
+    /// Adds a character to the end of the focused field.
+    pub fn insert(&mut self, character: char) {
+        self.focused_text_mut().push(character);
+    }
+
+    /// Removes the last character of the focused field, if there is one.
+    pub fn delete_backward(&mut self) {
+        self.focused_text_mut().pop();
+    }
+
+    fn focused_text_mut(&mut self) -> &mut String {
+        match self.focused_field {
+            FindField::Query => &mut self.query,
+            FindField::Replacement => &mut self.replacement,
+        }
+    }
+}
@@ -0,0 +1,585 @@
+//! Mapping keyboard input to editor commands.
+//!
+//! glutin reports each key press on its own without saying which modifiers are held, so
+//! [`Modifiers`][Modifiers] keeps track of the modifier keys as they're pressed and released. Each
+//! key press is combined with the current modifiers into a [`KeyChord`][KeyChord], and a binding in
+//! the [`Keymap`][Keymap] is a sequence of one or more chords. Sequences let commands be bound to
+//! things like "ctrl+k ctrl+w", where the first chord doesn't do anything on its own.
+//!
+//! Key sequences are written as space-separated chords, and each chord is a key name optionally
+//! preceded by modifiers joined with `+`, e.g. "ctrl+shift+tab" or "ctrl+k ctrl+w". The same
+//! format is used for the default keymap and for overriding it in the config file.
+//!
+//! [Modifiers]: struct.Modifiers.html
+//! [KeyChord]: struct.KeyChord.html
+//! [Keymap]: struct.Keymap.html
+
+use glutin::{ElementState, VirtualKeyCode};
+use std::collections::HashMap;
+use xi::EditCommand;
+
+/// The bindings that make up the default keymap.
+const DEFAULT_BINDINGS: &'static [(&'static str, Command)] = &[
+    ("enter", Command::InsertNewline),
+    ("backspace", Command::DeleteBackward),
+    ("delete", Command::DeleteForward),
+    ("left", Command::MoveLeft),
+    ("right", Command::MoveRight),
+    ("up", Command::MoveUp),
+    ("down", Command::MoveDown),
+    ("shift+left", Command::MoveLeftAndModifySelection),
+    ("shift+right", Command::MoveRightAndModifySelection),
+    ("shift+up", Command::MoveUpAndModifySelection),
+    ("shift+down", Command::MoveDownAndModifySelection),
+    ("home", Command::MoveToLeftEndOfLine),
+    ("end", Command::MoveToRightEndOfLine),
+    ("shift+home", Command::MoveToLeftEndOfLineAndModifySelection),
+    ("shift+end", Command::MoveToRightEndOfLineAndModifySelection),
+    ("pageup", Command::PageUp),
+    ("pagedown", Command::PageDown),
+    ("shift+pageup", Command::PageUpAndModifySelection),
+    ("shift+pagedown", Command::PageDownAndModifySelection),
+    ("ctrl+left", Command::MoveWordLeft),
+    ("ctrl+right", Command::MoveWordRight),
+    ("ctrl+shift+left", Command::MoveWordLeftAndModifySelection),
+    ("ctrl+shift+right", Command::MoveWordRightAndModifySelection),
+    ("ctrl+home", Command::MoveToBeginningOfDocument),
+    ("ctrl+end", Command::MoveToEndOfDocument),
+    ("ctrl+shift+home", Command::MoveToBeginningOfDocumentAndModifySelection),
+    ("ctrl+shift+end", Command::MoveToEndOfDocumentAndModifySelection),
+    ("ctrl+a", Command::SelectAll),
+    ("ctrl+c", Command::Copy),
+    ("ctrl+x", Command::Cut),
+    ("ctrl+v", Command::Paste),
+    ("ctrl+z", Command::Undo),
+    ("ctrl+shift+z", Command::Redo),
+    ("ctrl+y", Command::Redo),
+    ("ctrl+f", Command::Find),
+    ("ctrl+h", Command::Replace),
+    ("f3", Command::FindNext),
+    ("shift+f3", Command::FindPrevious),
+    ("ctrl+g", Command::GoToLine),
+    ("ctrl+s", Command::Save),
+    ("ctrl+shift+s", Command::SaveAs),
+    ("ctrl+t", Command::NewTab),
+    ("ctrl+w", Command::CloseTab),
+    ("ctrl+tab", Command::NextTab),
+    ("ctrl+pagedown", Command::NextTab),
+    ("ctrl+shift+tab", Command::PreviousTab),
+    ("ctrl+pageup", Command::PreviousTab),
+    ("ctrl+backslash", Command::SplitVertical),
+    ("ctrl+minus", Command::SplitHorizontal),
+    ("f6", Command::FocusNextPane),
+    ("shift+f6", Command::FocusPreviousPane),
+];
+
+/// A named editor command that can be bound to a key sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    InsertNewline,
+    DeleteBackward,
+    DeleteForward,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    MoveLeftAndModifySelection,
+    MoveRightAndModifySelection,
+    MoveUpAndModifySelection,
+    MoveDownAndModifySelection,
+
+    /// Moves to the first non-whitespace character in the line, or to the start of the line if
+    /// the cursor is already there.
+    MoveToLeftEndOfLine,
+
+    MoveToRightEndOfLine,
+    MoveToLeftEndOfLineAndModifySelection,
+    MoveToRightEndOfLineAndModifySelection,
+    MoveWordLeft,
+    MoveWordRight,
+    MoveWordLeftAndModifySelection,
+    MoveWordRightAndModifySelection,
+    MoveToBeginningOfDocument,
+    MoveToEndOfDocument,
+    MoveToBeginningOfDocumentAndModifySelection,
+    MoveToEndOfDocumentAndModifySelection,
+
+    /// Moves up by the number of lines visible in the view.
+    PageUp,
+
+    /// Moves down by the number of lines visible in the view.
+    PageDown,
+
+    PageUpAndModifySelection,
+    PageDownAndModifySelection,
+    SelectAll,
+
+    /// Copies the selected text to the clipboard.
+    Copy,
+
+    /// Copies the selected text to the clipboard and removes it from the document.
+    Cut,
+
+    /// Inserts the contents of the clipboard at each cursor.
+    Paste,
+
+    Undo,
+    Redo,
+
+    /// Opens the find panel.
+    Find,
+
+    /// Opens the find panel with the replacement field shown.
+    Replace,
+
+    /// Selects the next match of the last search.
+    FindNext,
+
+    /// Selects the previous match of the last search.
+    FindPrevious,
+
+    /// Asks for a line to move the cursor to.
+    GoToLine,
+
+    /// Saves the current tab, asking for a path if the document is untitled.
+    Save,
+
+    /// Asks for a path and saves the current tab there.
+    SaveAs,
+
+    NewTab,
+    CloseTab,
+    NextTab,
+    PreviousTab,
+
+    /// Splits the focused pane so the new pane is to the right.
+    SplitVertical,
+
+    /// Splits the focused pane so the new pane is below.
+    SplitHorizontal,
+
+    FocusNextPane,
+    FocusPreviousPane,
+}
+
+impl Command {
+    /// Looks up a command by the name used for it in the config file, e.g. "move_left".
+    pub fn from_name(name: &str) -> Option<Command> {
+        let command = match name {
+            "insert_newline" => Command::InsertNewline,
+            "delete_backward" => Command::DeleteBackward,
+            "delete_forward" => Command::DeleteForward,
+            "move_left" => Command::MoveLeft,
+            "move_right" => Command::MoveRight,
+            "move_up" => Command::MoveUp,
+            "move_down" => Command::MoveDown,
+            "move_left_and_modify_selection" => Command::MoveLeftAndModifySelection,
+            "move_right_and_modify_selection" => Command::MoveRightAndModifySelection,
+            "move_up_and_modify_selection" => Command::MoveUpAndModifySelection,
+            "move_down_and_modify_selection" => Command::MoveDownAndModifySelection,
+            "move_to_left_end_of_line" => Command::MoveToLeftEndOfLine,
+            "move_to_right_end_of_line" => Command::MoveToRightEndOfLine,
+            "move_to_left_end_of_line_and_modify_selection" => Command::MoveToLeftEndOfLineAndModifySelection,
+            "move_to_right_end_of_line_and_modify_selection" => Command::MoveToRightEndOfLineAndModifySelection,
+            "move_word_left" => Command::MoveWordLeft,
+            "move_word_right" => Command::MoveWordRight,
+            "move_word_left_and_modify_selection" => Command::MoveWordLeftAndModifySelection,
+            "move_word_right_and_modify_selection" => Command::MoveWordRightAndModifySelection,
+            "move_to_beginning_of_document" => Command::MoveToBeginningOfDocument,
+            "move_to_end_of_document" => Command::MoveToEndOfDocument,
+            "move_to_beginning_of_document_and_modify_selection" => Command::MoveToBeginningOfDocumentAndModifySelection,
+            "move_to_end_of_document_and_modify_selection" => Command::MoveToEndOfDocumentAndModifySelection,
+            "page_up" => Command::PageUp,
+            "page_down" => Command::PageDown,
+            "page_up_and_modify_selection" => Command::PageUpAndModifySelection,
+            "page_down_and_modify_selection" => Command::PageDownAndModifySelection,
+            "select_all" => Command::SelectAll,
+            "copy" => Command::Copy,
+            "cut" => Command::Cut,
+            "paste" => Command::Paste,
+            "undo" => Command::Undo,
+            "redo" => Command::Redo,
+            "find" => Command::Find,
+            "replace" => Command::Replace,
+            "find_next" => Command::FindNext,
+            "find_previous" => Command::FindPrevious,
+            "go_to_line" => Command::GoToLine,
+            "save" => Command::Save,
+            "save_as" => Command::SaveAs,
+            "new_tab" => Command::NewTab,
+            "close_tab" => Command::CloseTab,
+            "next_tab" => Command::NextTab,
+            "previous_tab" => Command::PreviousTab,
+            "split_vertical" => Command::SplitVertical,
+            "split_horizontal" => Command::SplitHorizontal,
+            "focus_next_pane" => Command::FocusNextPane,
+            "focus_previous_pane" => Command::FocusPreviousPane,
+            _ => return None,
+        };
+
+        Some(command)
+    }
+
+    /// Returns the xi-core edit command that performs this command, if it's handled entirely by
+    /// xi-core.
+    pub fn edit_command(&self) -> Option<EditCommand<'static>> {
+        let edit_command = match *self {
+            Command::InsertNewline => EditCommand::InsertNewline,
+            Command::DeleteBackward => EditCommand::DeleteBackward,
+            Command::DeleteForward => EditCommand::DeleteForward,
+            Command::MoveLeft => EditCommand::MoveLeft,
+            Command::MoveRight => EditCommand::MoveRight,
+            Command::MoveUp => EditCommand::MoveUp,
+            Command::MoveDown => EditCommand::MoveDown,
+            Command::MoveLeftAndModifySelection => EditCommand::MoveLeftAndModifySelection,
+            Command::MoveRightAndModifySelection => EditCommand::MoveRightAndModifySelection,
+            Command::MoveUpAndModifySelection => EditCommand::MoveUpAndModifySelection,
+            Command::MoveDownAndModifySelection => EditCommand::MoveDownAndModifySelection,
+            Command::MoveToRightEndOfLine => EditCommand::MoveToRightEndOfLine,
+            Command::MoveToRightEndOfLineAndModifySelection => EditCommand::MoveToRightEndOfLineAndModifySelection,
+            Command::MoveWordLeft => EditCommand::MoveWordLeft,
+            Command::MoveWordRight => EditCommand::MoveWordRight,
+            Command::MoveWordLeftAndModifySelection => EditCommand::MoveWordLeftAndModifySelection,
+            Command::MoveWordRightAndModifySelection => EditCommand::MoveWordRightAndModifySelection,
+            Command::MoveToBeginningOfDocument => EditCommand::MoveToBeginningOfDocument,
+            Command::MoveToEndOfDocument => EditCommand::MoveToEndOfDocument,
+            Command::MoveToBeginningOfDocumentAndModifySelection => EditCommand::MoveToBeginningOfDocumentAndModifySelection,
+            Command::MoveToEndOfDocumentAndModifySelection => EditCommand::MoveToEndOfDocumentAndModifySelection,
+            Command::PageUp => EditCommand::ScrollPageUp,
+            Command::PageDown => EditCommand::ScrollPageDown,
+            Command::PageUpAndModifySelection => EditCommand::PageUpAndModifySelection,
+            Command::PageDownAndModifySelection => EditCommand::PageDownAndModifySelection,
+            Command::SelectAll => EditCommand::SelectAll,
+            Command::Undo => EditCommand::Undo,
+            Command::Redo => EditCommand::Redo,
+            _ => return None,
+        };
+
+        Some(edit_command)
+    }
+}
+
+/// The modifier keys that are currently held down.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+
+    /// The Windows key on Windows and Linux, or Command on macOS.
+    pub logo: bool,
+}
+
+impl Modifiers {
+    /// Updates the modifiers for a key being pressed or released.
+    ///
+    /// Returns `true` if the key was a modifier key.
+    pub fn update(&mut self, state: ElementState, key: VirtualKeyCode) -> bool {
+        let pressed = state == ElementState::Pressed;
+        match key {
+            VirtualKeyCode::LControl | VirtualKeyCode::RControl => self.ctrl = pressed,
+            VirtualKeyCode::LShift | VirtualKeyCode::RShift => self.shift = pressed,
+            VirtualKeyCode::LAlt | VirtualKeyCode::RAlt | VirtualKeyCode::LMenu | VirtualKeyCode::RMenu => {
+                self.alt = pressed
+            }
+            VirtualKeyCode::LWin | VirtualKeyCode::RWin => self.logo = pressed,
+            _ => return false,
+        }
+
+        true
+    }
+}
+
+/// A single key pressed while holding some set of modifiers, e.g. Ctrl+Shift+Tab.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub modifiers: Modifiers,
+    pub key: VirtualKeyCode,
+}
+
+impl KeyChord {
+    pub fn new(modifiers: Modifiers, key: VirtualKeyCode) -> KeyChord {
+        KeyChord {
+            modifiers: modifiers,
+            key: key,
+        }
+    }
+
+    /// Parses a single chord, e.g. "ctrl+shift+tab".
+    ///
+    /// Returns `None` if the chord names an unknown key or modifier.
+    pub fn parse(chord: &str) -> Option<KeyChord> {
+        let mut parts: Vec<&str> = chord.split('+').collect();
+        let key = match parts.pop().and_then(|key| key_from_name(&*key.to_lowercase())) {
+            Some(key) => key,
+            None => return None,
+        };
+
+        let mut modifiers = Modifiers::default();
+        for modifier in parts {
+            match &*modifier.to_lowercase() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "shift" => modifiers.shift = true,
+                "alt" => modifiers.alt = true,
+                "logo" | "super" | "cmd" => modifiers.logo = true,
+                _ => return None,
+            }
+        }
+
+        Some(KeyChord::new(modifiers, key))
+    }
+}
+
+/// Parses a space-separated sequence of chords, e.g. "ctrl+k ctrl+w".
+///
+/// Returns `None` if the sequence is empty or any of its chords can't be parsed.
+pub fn parse_keys(keys: &str) -> Option<Vec<KeyChord>> {
+    let chords = keys.split_whitespace().map(KeyChord::parse).collect::<Option<Vec<_>>>();
+    match chords {
+        Some(ref chords) if chords.is_empty() => None,
+        chords => chords,
+    }
+}
+
+/// The result of looking up a key sequence in the keymap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    /// The sequence is bound to a command.
+    Command(Command),
+
+    /// The sequence is the start of one or more longer bindings, so more keys are needed.
+    Prefix,
+
+    /// Nothing is bound to the sequence.
+    Unbound,
+}
+
+/// The set of key bindings.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<Vec<KeyChord>, Command>,
+}
+
+impl Keymap {
+    /// Creates a keymap with no bindings.
+    pub fn new() -> Keymap {
+        Keymap {
+            bindings: HashMap::new(),
+        }
+    }
+
+    /// Binds a key sequence to a command, replacing any existing binding for the sequence.
+    pub fn bind(&mut self, keys: Vec<KeyChord>, command: Command) {
+        self.bindings.insert(keys, command);
+    }
+
+    /// Removes the binding for a key sequence, if there is one.
+    pub fn unbind(&mut self, keys: &[KeyChord]) {
+        self.bindings.remove(keys);
+    }
+
+    /// Looks up the binding for a sequence of keys pressed so far.
+    ///
+    /// Holding Shift on its own doesn't change what a key does unless there's a binding for it,
+    /// e.g. Shift+Enter still inserts a newline.
+    pub fn lookup(&self, keys: &[KeyChord]) -> Binding {
+        if let Some(&command) = self.bindings.get(keys) {
+            return Binding::Command(command);
+        }
+
+        if self.bindings.keys().any(|bound| bound.len() > keys.len() && bound.starts_with(keys)) {
+            return Binding::Prefix;
+        }
+
+        let only_shift = Modifiers {
+            shift: true,
+            .. Modifiers::default()
+        };
+        match keys.last() {
+            Some(last) if last.modifiers == only_shift => {
+                let mut unshifted = keys.to_vec();
+                unshifted.last_mut().unwrap().modifiers.shift = false;
+                self.lookup(&*unshifted)
+            }
+            _ => Binding::Unbound,
+        }
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        let mut keymap = Keymap::new();
+        for &(keys, command) in DEFAULT_BINDINGS {
+            let keys = parse_keys(keys).expect("Invalid key sequence in default keymap");
+            keymap.bind(keys, command);
+        }
+
+        keymap
+    }
+}
+
+/// Looks up a key by the name used for it in key sequences.
+fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+    let key = match name {
+        "a" => VirtualKeyCode::A,
+        "b" => VirtualKeyCode::B,
+        "c" => VirtualKeyCode::C,
+        "d" => VirtualKeyCode::D,
+        "e" => VirtualKeyCode::E,
+        "f" => VirtualKeyCode::F,
+        "g" => VirtualKeyCode::G,
+        "h" => VirtualKeyCode::H,
+        "i" => VirtualKeyCode::I,
+        "j" => VirtualKeyCode::J,
+        "k" => VirtualKeyCode::K,
+        "l" => VirtualKeyCode::L,
+        "m" => VirtualKeyCode::M,
+        "n" => VirtualKeyCode::N,
+        "o" => VirtualKeyCode::O,
+        "p" => VirtualKeyCode::P,
+        "q" => VirtualKeyCode::Q,
+        "r" => VirtualKeyCode::R,
+        "s" => VirtualKeyCode::S,
+        "t" => VirtualKeyCode::T,
+        "u" => VirtualKeyCode::U,
+        "v" => VirtualKeyCode::V,
+        "w" => VirtualKeyCode::W,
+        "x" => VirtualKeyCode::X,
+        "y" => VirtualKeyCode::Y,
+        "z" => VirtualKeyCode::Z,
+        "0" => VirtualKeyCode::Key0,
+        "1" => VirtualKeyCode::Key1,
+        "2" => VirtualKeyCode::Key2,
+        "3" => VirtualKeyCode::Key3,
+        "4" => VirtualKeyCode::Key4,
+        "5" => VirtualKeyCode::Key5,
+        "6" => VirtualKeyCode::Key6,
+        "7" => VirtualKeyCode::Key7,
+        "8" => VirtualKeyCode::Key8,
+        "9" => VirtualKeyCode::Key9,
+        "f1" => VirtualKeyCode::F1,
+        "f2" => VirtualKeyCode::F2,
+        "f3" => VirtualKeyCode::F3,
+        "f4" => VirtualKeyCode::F4,
+        "f5" => VirtualKeyCode::F5,
+        "f6" => VirtualKeyCode::F6,
+        "f7" => VirtualKeyCode::F7,
+        "f8" => VirtualKeyCode::F8,
+        "f9" => VirtualKeyCode::F9,
+        "f10" => VirtualKeyCode::F10,
+        "f11" => VirtualKeyCode::F11,
+        "f12" => VirtualKeyCode::F12,
+        "escape" | "esc" => VirtualKeyCode::Escape,
+        "enter" | "return" => VirtualKeyCode::Return,
+        "backspace" => VirtualKeyCode::Back,
+        "delete" | "del" => VirtualKeyCode::Delete,
+        "insert" => VirtualKeyCode::Insert,
+        "tab" => VirtualKeyCode::Tab,
+        "space" => VirtualKeyCode::Space,
+        "left" => VirtualKeyCode::Left,
+        "right" => VirtualKeyCode::Right,
+        "up" => VirtualKeyCode::Up,
+        "down" => VirtualKeyCode::Down,
+        "home" => VirtualKeyCode::Home,
+        "end" => VirtualKeyCode::End,
+        "pageup" => VirtualKeyCode::PageUp,
+        "pagedown" => VirtualKeyCode::PageDown,
+        "minus" => VirtualKeyCode::Minus,
+        "equals" => VirtualKeyCode::Equals,
+        "backslash" => VirtualKeyCode::Backslash,
+        "slash" => VirtualKeyCode::Slash,
+        "comma" => VirtualKeyCode::Comma,
+        "period" => VirtualKeyCode::Period,
+        "semicolon" => VirtualKeyCode::Semicolon,
+        "apostrophe" => VirtualKeyCode::Apostrophe,
+        "grave" => VirtualKeyCode::Grave,
+        "lbracket" => VirtualKeyCode::LBracket,
+        "rbracket" => VirtualKeyCode::RBracket,
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(ctrl: bool, shift: bool, key: VirtualKeyCode) -> KeyChord {
+        let modifiers = Modifiers {
+            ctrl: ctrl,
+            shift: shift,
+            .. Modifiers::default()
+        };
+        KeyChord::new(modifiers, key)
+    }
+
+    #[test]
+    fn parse_keys_with_modifiers() {
+        assert_eq!(parse_keys("ctrl+shift+tab"), Some(vec![chord(true, true, VirtualKeyCode::Tab)]));
+        assert_eq!(parse_keys("Ctrl+S"), Some(vec![chord(true, false, VirtualKeyCode::S)]));
+        assert_eq!(parse_keys("f3"), Some(vec![chord(false, false, VirtualKeyCode::F3)]));
+    }
+
+    #[test]
+    fn parse_keys_sequence() {
+        assert_eq!(
+            parse_keys("ctrl+k  ctrl+w"),
+            Some(vec![chord(true, false, VirtualKeyCode::K), chord(true, false, VirtualKeyCode::W)])
+        );
+    }
+
+    #[test]
+    fn parse_keys_invalid() {
+        assert_eq!(parse_keys(""), None);
+        assert_eq!(parse_keys("   "), None);
+        assert_eq!(parse_keys("ctrl+"), None);
+        assert_eq!(parse_keys("hyper+a"), None);
+        assert_eq!(parse_keys("ctrl+k nonsense"), None);
+    }
+
+    #[test]
+    fn lookup_sequences() {
+        let mut keymap = Keymap::new();
+        keymap.bind(parse_keys("ctrl+k ctrl+w").unwrap(), Command::CloseTab);
+        keymap.bind(parse_keys("ctrl+s").unwrap(), Command::Save);
+
+        let ctrl_k = chord(true, false, VirtualKeyCode::K);
+        let ctrl_w = chord(true, false, VirtualKeyCode::W);
+        assert_eq!(keymap.lookup(&[chord(true, false, VirtualKeyCode::S)]), Binding::Command(Command::Save));
+        assert_eq!(keymap.lookup(&[ctrl_k]), Binding::Prefix);
+        assert_eq!(keymap.lookup(&[ctrl_k, ctrl_w]), Binding::Command(Command::CloseTab));
+        assert_eq!(keymap.lookup(&[ctrl_w]), Binding::Unbound);
+        assert_eq!(keymap.lookup(&[ctrl_k, ctrl_k]), Binding::Unbound);
+    }
+
+    #[test]
+    fn lookup_falls_back_to_key_without_shift() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.lookup(&[chord(false, true, VirtualKeyCode::Return)]),
+            Binding::Command(Command::InsertNewline)
+        );
+        assert_eq!(
+            keymap.lookup(&[chord(false, true, VirtualKeyCode::Back)]),
+            Binding::Command(Command::DeleteBackward)
+        );
+
+        // Bindings that use Shift take precedence, and other modifiers don't fall back.
+        assert_eq!(
+            keymap.lookup(&[chord(false, true, VirtualKeyCode::Left)]),
+            Binding::Command(Command::MoveLeftAndModifySelection)
+        );
+        assert_eq!(keymap.lookup(&[chord(true, true, VirtualKeyCode::Return)]), Binding::Unbound);
+    }
+
+    #[test]
+    fn unbind_removes_binding() {
+        let mut keymap = Keymap::default();
+        let ctrl_w = parse_keys("ctrl+w").unwrap();
+        assert_eq!(keymap.lookup(&*ctrl_w), Binding::Command(Command::CloseTab));
+
+        keymap.unbind(&*ctrl_w);
+        assert_eq!(keymap.lookup(&*ctrl_w), Binding::Unbound);
+    }
+}
@@ -0,0 +1,136 @@
+//! Locations in a document that can be jumped to, e.g. from the go-to-line prompt or the command
+//! line.
+//!
+//! Locations are written the way compilers and grep report them, as `line` or `line:column`, and
+//! count from 1. On the command line a location can also be attached to a file, as in
+//! `src/main.rs:12:4`.
+
+use std::path::Path;
+
+/// A line and column in a document, both counting from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    /// Parses a location written as `line` or `line:column`, e.g. "12:4". The column defaults to
+    /// the start of the line.
+    ///
+    /// Returns `None` if either number is missing or isn't a positive integer.
+    pub fn parse(text: &str) -> Option<Location> {
+        let mut parts = text.trim().splitn(2, ':');
+
+        let line = match parts.next().and_then(parse_number) {
+            Some(line) => line,
+            None => return None,
+        };
+
+        let column = match parts.next() {
+            Some(column) => {
+                match parse_number(column) {
+                    Some(column) => column,
+                    None => return None,
+                }
+            }
+            None => 1,
+        };
+
+        Some(Location {
+            line: line,
+            column: column,
+        })
+    }
+}
+
+/// Splits a location off the end of a file path, e.g. "src/main.rs:12:4" is split into
+/// "src/main.rs" and line 12, column 4.
+///
+/// Paths that already exist are left alone, in case the file name itself contains colons.
+pub fn split_file_location(arg: &str) -> (&str, Option<Location>) {
+    if Path::new(arg).exists() {
+        return (arg, None);
+    }
+
+    // Try splitting off one part and then two, keeping the longest split that's still a valid
+    // location.
+    let mut result = (arg, None);
+    let mut split = arg.len();
+    for _ in 0..2 {
+        split = match arg[..split].rfind(':') {
+            Some(split) => split,
+            None => break,
+        };
+
+        match Location::parse(&arg[split + 1..]) {
+            Some(location) => result = (&arg[..split], Some(location)),
+            None => break,
+        }
+    }
+
+    result
+}
+
+/// Parses a number that counts from 1.
+fn parse_number(text: &str) -> Option<usize> {
+    match text.parse() {
+        Ok(0) | Err(_) => None,
+        Ok(number) => Some(number),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(line: usize, column: usize) -> Location {
+        Location {
+            line: line,
+            column: column,
+        }
+    }
+
+    #[test]
+    fn parse_line() {
+        assert_eq!(Location::parse("12"), Some(location(12, 1)));
+        assert_eq!(Location::parse(" 12 "), Some(location(12, 1)));
+    }
+
+    #[test]
+    fn parse_line_and_column() {
+        assert_eq!(Location::parse("12:4"), Some(location(12, 4)));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert_eq!(Location::parse(""), None);
+        assert_eq!(Location::parse("0"), None);
+        assert_eq!(Location::parse("12:0"), None);
+        assert_eq!(Location::parse("12:"), None);
+        assert_eq!(Location::parse("-3"), None);
+        assert_eq!(Location::parse("abc"), None);
+        assert_eq!(Location::parse("1:2:3"), None);
+    }
+
+    #[test]
+    fn split_file_location_without_location() {
+        assert_eq!(split_file_location("does/not/exist.rs"), ("does/not/exist.rs", None));
+    }
+
+    #[test]
+    fn split_file_location_with_line() {
+        assert_eq!(split_file_location("does/not/exist.rs:12"), ("does/not/exist.rs", Some(location(12, 1))));
+    }
+
+    #[test]
+    fn split_file_location_with_line_and_column() {
+        assert_eq!(split_file_location("does/not/exist.rs:12:4"), ("does/not/exist.rs", Some(location(12, 4))));
+    }
+
+    #[test]
+    fn split_file_location_keeps_colons_that_arent_locations() {
+        assert_eq!(split_file_location("does:not:exist.rs"), ("does:not:exist.rs", None));
+        assert_eq!(split_file_location("does:not:exist.rs:12"), ("does:not:exist.rs", Some(location(12, 1))));
+    }
+}
@@ -3,6 +3,7 @@ extern crate clap;
 extern crate glutin;
 extern crate gleam;
 extern crate rusttype;
+extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_json;
@@ -16,11 +17,36 @@ use glutin::*;
 use webrender_traits::*;
 use rusttype::*;
 use serde_json::Value;
+use std::cmp;
+use std::env;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Read, Write};
-use std::process::{Command, Stdio};
-use std::sync::mpsc;
-use std::thread;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{self, Stdio};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, TryRecvError};
+use std::time::{Duration, Instant};
+use animation::{Animation, Momentum, VelocityTracker};
+use clipboard::Selection;
+use config::Config;
+use find::{FindField, FindPanel};
+use keymap::{Binding, Command, KeyChord, Modifiers};
+use location::Location;
+use pane::{Pane, Panes, SplitDirection};
+use prompt::{Prompt, PromptKind};
+use timer::Timer;
+use xi::{EditCommand, Notification, Response, XiClient};
+
+mod animation;
+mod clipboard;
+mod config;
+mod cursor;
+mod find;
+mod keymap;
+mod location;
+mod pane;
+mod prompt;
+mod timer;
+mod xi;
 
 /// The font size in pixels (measuring the vertical height of the font).
 const FONT_SIZE_PX: f32 = 15.0;
@@ -33,18 +59,80 @@ const LINE_HEIGHT: f32 = 1.5;
 /// See https://github.com/excaliburHisSheath/text-edit/issues/4 for more info.
 const PIXEL_TO_POINT: f32 = 0.75;
 
+/// The height of the tab bar at the top of the window, in pixels.
+const TAB_BAR_HEIGHT: f32 = 30.0;
+
+/// The horizontal space between the edge of a tab and its label, in pixels.
+const TAB_PADDING: f32 = 12.0;
+
+/// The horizontal space on either side of the line numbers in the gutter, in pixels.
+const GUTTER_PADDING: f32 = 10.0;
+
+/// The height of the prompt shown along the bottom of the window, in pixels.
+const PROMPT_HEIGHT: f32 = 30.0;
+
+/// The height of each row of the find panel, in pixels.
+const FIND_PANEL_ROW_HEIGHT: f32 = 30.0;
+
+/// The height of the status bar along the bottom of the window, in pixels.
+const STATUS_BAR_HEIGHT: f32 = 30.0;
+
+/// The number of lines scrolled for each step of a mouse wheel.
+const SCROLL_LINES_PER_STEP: f32 = 3.0;
+
+/// The longest time between two clicks for them to count as a double or triple click, in
+/// milliseconds.
+const MULTI_CLICK_INTERVAL_MS: u64 = 500;
+
+/// How far the mouse can move between two clicks for them to count as a double or triple click,
+/// in pixels.
+const MULTI_CLICK_DISTANCE: f32 = 4.0;
+
 /// Enables debug rendering of glyph bounding boxes.
 const DEBUG_GLYPHS: bool = false;
 
+/// How long to wait for xi-core to respond to a request that the UI can't carry on without, e.g.
+/// opening a new tab, before giving up on it.
+const XI_RESPONSE_TIMEOUT_MS: u64 = 5000;
+
 fn main() {
     let matches = App::new("text-edit")
         .version("0.1")
         .author("David LeGare <excaliburhissheath@gmail.com>")
         .about("Edits text poorly")
-        .arg(Arg::with_name("file").required(false))
+        .arg(Arg::with_name("config")
+            .long("config")
+            .takes_value(true)
+            .value_name("PATH")
+            .help("Loads settings from the given config file"))
+        .arg(Arg::with_name("file")
+            .multiple(true)
+            .required(false)
+            .help("Files to open, optionally followed by :LINE[:COLUMN] or preceded by +LINE"))
         .get_matches();
 
-    let initial_file = matches.value_of("file");
+    // Each file can be opened at a location, given either after the path as in "file:12:4" or as a
+    // separate "+12" before the path.
+    let mut initial_files: Vec<(&str, Option<Location>)> = Vec::new();
+    let mut next_location: Option<(&str, Location)> = None;
+    for arg in matches.values_of("file").into_iter().flat_map(|files| files) {
+        if arg.starts_with('+') {
+            if let Some(location) = Location::parse(&arg[1..]) {
+                if let Some((ignored, _)) = next_location {
+                    eprintln!("Ignoring {}, since it isn't followed by a file", ignored);
+                }
+                next_location = Some((arg, location));
+                continue;
+            }
+        }
+
+        let (file_path, location) = location::split_file_location(arg);
+        let preceding_location = next_location.take().map(|(_, location)| location);
+        initial_files.push((file_path, location.or(preceding_location)));
+    }
+    if let Some((ignored, _)) = next_location {
+        eprintln!("Ignoring {}, since it isn't followed by a file", ignored);
+    }
 
     // Load sample font into memory for layout purposes.
     let mut file = File::open("res/Hack-Regular.ttf").unwrap();
@@ -53,10 +141,30 @@ fn main() {
 
     let font = FontCollection::from_bytes(&*font_bytes).into_font().unwrap();
 
+    // A message for the user shown in the status bar, e.g. when something they asked for failed.
+    let mut message: Option<String> = None;
+
+    // Load the config file, falling back to the default settings if there isn't one. It's only an
+    // error for the file to be missing if it was explicitly given on the command line.
+    let config_path = matches.value_of("config").map(PathBuf::from).or_else(Config::default_path);
+    let config = match config_path {
+        Some(ref config_path) if matches.is_present("config") || config_path.exists() => {
+            match Config::load(config_path) {
+                Ok(config) => config,
+                Err(error) => {
+                    eprintln!("Failed to load {}, using default config: {}", config_path.display(), error);
+                    message = Some(format!("Failed to load {}: {}", config_path.display(), error));
+                    Config::default()
+                }
+            }
+        }
+        _ => Config::default(),
+    };
+
     // Create a new glutin window and make its OpenGL context active.
     // ============================================================================================
     let window = WindowBuilder::new()
-                .with_title("WebRender Sample")
+                .with_title("text-edit")
                 .with_gl(GlRequest::Specific(Api::OpenGl, (3, 2)))
                 .build()
                 .unwrap();
@@ -101,188 +209,737 @@ fn main() {
     // ============================================================================================
 
     // TODO: This currently requires that xi-core be in the system PATH
-    let xi_process = Command::new("xi-core")
+    let xi_process = process::Command::new("xi-core")
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .spawn()
         .expect("Somehow failed to run xi-core command, maybe it's not installed?");
 
     // Get input and output pipes for xi-core.
-    let mut xi_stdin = xi_process.stdin.expect("No stdin pipe to xi-core child process");
+    let xi_stdin = xi_process.stdin.expect("No stdin pipe to xi-core child process");
     let xi_stdout = xi_process.stdout.expect("No stdout pipe to xi-core child process");
     let xi_stdout = BufReader::new(xi_stdout);
 
-    // Open a tab.
-    // TODO: Actually track the name of the new tab and use it in future messages.
-    writeln!(xi_stdin, "{}", r#"{"id":0,"method":"new_tab","params":[]}"#).expect("Failed to send message to xi-core");
-
-    // Change the visible region of the file (no response).
-    let (window_width, window_height) = window.get_inner_size().unwrap();
-    let window_height_in_lines = window_height as f32 / (FONT_SIZE_PX * LINE_HEIGHT);
-    writeln!(xi_stdin, r#"{{"method":"edit","params":{{"method":"scroll","params":[0, {}],"tab":"0"}}}}"#, window_height_in_lines as usize).expect("Failed to send message to xi-core");
+    // Connect to xi-core, waking up the event loop whenever it sends us a message.
+    let window_proxy = window.create_window_proxy();
+    let (mut xi, notifications) = XiClient::new(xi_stdin, xi_stdout, move || window_proxy.wakeup_event_loop());
+
+    // Open a tab for each file given on the command line, or a single empty tab if there weren't
+    // any.
+    let (mut window_width, mut window_height) = window.get_inner_size().unwrap();
+    let mut editors = Vec::new();
+    for &(file_path, location) in &initial_files {
+        let mut editor = match open_editor(&mut xi, Some(file_path), window_height) {
+            Ok(editor) => editor,
+            Err(error) => {
+                eprintln!("{}", error);
+                process::exit(1);
+            }
+        };
+        if let Some(location) = location {
+            go_to_location(&mut xi, &mut editor, location);
+        }
+        editors.push(editor);
+    }
+    if editors.is_empty() {
+        match open_editor(&mut xi, None, window_height) {
+            Ok(editor) => editors.push(editor),
+            Err(error) => {
+                eprintln!("{}", error);
+                process::exit(1);
+            }
+        }
+    }
 
-    // Open this file and get the lines from the file.
-    if let Some(file_path) = initial_file {
-        // TODO: Windows-style path separators cause this to fail.
-        // Issue tracker: https://github.com/excaliburHisSheath/text-edit/issues/5
-        writeln!(xi_stdin, r#"{{"method":"edit","params":{{"method":"open","params":{{"filename":"{}"}},"tab":"0"}}}}"#, file_path).expect("Failed to send message to xi-core");
+    let mut editors = editors.into_iter();
+    let mut pane = Pane::new(editors.next().unwrap());
+    for editor in editors {
+        pane.add_editor(editor);
     }
+    pane.active_editor = 0;
 
-    // Spawn a thread to pull the responses form xi-core.
-    let (sender, receiver) = mpsc::channel();
-    let window_proxy = window.create_window_proxy();
-    thread::spawn(move || {
-        for result in xi_stdout.lines() {
-            // Read response from creating the tab.
-            let response = result.expect("Error receiving response from xi-core");
-            sender.send(response).expect("Failed to send message to main thread");
-            window_proxy.wakeup_event_loop();
-        }
-    });
+    // The window starts out with a single pane, which can then be split.
+    let mut panes = Panes::new(pane);
 
-    let mut editor = EditorState {
-        height_in_lines: 0,
-        first_line: 0,
-        lines: vec![
-            LineContents {
-                text: "".into(),
-                cursors: vec![0],
-                selections: Vec::new(),
-            },
-        ],
-        view_width_pixels: window_width as usize,
-        view_height_pixels: window_height as usize,
-        scroll_offset_pixels: 0.0,
-    };
+    let mut window_title = window_title_for_editor(panes.focused().active_editor());
+    window.set_title(&*window_title);
 
     // Generate initial frame.
     let builder = build_display_lists(
         pipeline_id,
         font_key,
         &font,
-        &mut editor,
+        &config,
+        &mut panes,
+        true,
+        true,
+        &FindPanel::new(),
         None,
+        message.as_ref().map(|message| &**message),
+        LayoutSize::new(window_width as f32, window_height as f32),
     );
     api.set_root_display_list(
         Some(root_background_color),
         epoch,
-        LayoutSize::new(editor.view_width_pixels as f32, editor.view_height_pixels as f32),
+        LayoutSize::new(window_width as f32, window_height as f32),
         builder,
     );
     api.generate_frame();
 
+    // Used to wake up the event loop to draw the next frame of an animation.
+    let animation_proxy = window.create_window_proxy();
+
+    let mut clipboard = clipboard::system_clipboard();
+
+    // Used to wake up the event loop for anything that needs to happen at a particular time, e.g.
+    // blinking the cursor.
+    let timer_proxy = window.create_window_proxy();
+    let timer = Timer::new(move || timer_proxy.wakeup_event_loop());
+
     // Main event loop.
     // =============================================================================================
     let mut dirty = false;
+    let mut scrolled = false;
+    let mut modifiers = Modifiers::default();
+    let mut pending_keys = Vec::new();
+    let mut suppress_character = false;
+    let mut mouse_position = LayoutPoint::new(0.0, 0.0);
+    let mut last_click: Option<(Instant, LayoutPoint)> = None;
+    let mut click_count = 0;
+    let mut dragging_pane: Option<usize> = None;
+    let mut trackpad_velocity = VelocityTracker::new();
+    let mut window_focused = true;
+    let mut cursor_blink = cursor::Blink::new(config.cursor_blink_interval, Instant::now());
+    let mut drawn_cursor_visible = true;
+    let mut blink_wakeup: Option<Instant> = None;
+    let mut prompt: Option<Prompt> = None;
+    let mut find_panel = FindPanel::new();
+    let mut pending_copies: Vec<PendingCopy> = Vec::new();
     for event in window.wait_events() {
+        // Keep the cursor shown while typing or clicking.
+        match event {
+            Event::KeyboardInput(ElementState::Pressed, _, _)
+            | Event::ReceivedCharacter(_)
+            | Event::MouseInput(ElementState::Pressed, _) => cursor_blink.reset(Instant::now()),
+            _ => {}
+        }
+
+        // Messages stay up until the next key press or click.
+        match event {
+            Event::KeyboardInput(ElementState::Pressed, _, _)
+            | Event::MouseInput(ElementState::Pressed, _) => {
+                if message.take().is_some() {
+                    dirty = true;
+                }
+            }
+            _ => {}
+        }
+
         match event {
-            Event::Closed => return,
-            Event::KeyboardInput(element_state, _scan_code, virtual_key_code) => {
-                if element_state == ElementState::Pressed {
-                    if let Some(virtual_key_code) = virtual_key_code {
-                        let message = match virtual_key_code {
-                            VirtualKeyCode::Return => Some(r#"{"method":"edit","params":{"method":"insert_newline","params":{},"tab":"0"}}"#),
-                            VirtualKeyCode::Back => Some(r#"{"method":"edit","params":{"method":"delete_backward","params":{},"tab":"0"}}"#),
-                            VirtualKeyCode::Delete => Some(r#"{"method":"edit","params":{"method":"delete_forward","params":{},"tab":"0"}}"#),
-                            VirtualKeyCode::Left => Some(r#"{"method":"edit","params":{"method":"move_left","params":{},"tab":"0"}}"#),
-                            VirtualKeyCode::Right => Some(r#"{"method":"edit","params":{"method":"move_right","params":{},"tab":"0"}}"#),
-                            VirtualKeyCode::Up => Some(r#"{"method":"edit","params":{"method":"move_up","params":{},"tab":"0"}}"#),
-                            VirtualKeyCode::Down => Some(r#"{"method":"edit","params":{"method":"move_down","params":{},"tab":"0"}}"#),
-                            _ => None,
+            Event::Closed => {
+                // Give the user a chance to save their work before closing.
+                let unsaved_changes = panes.panes()
+                    .iter()
+                    .any(|pane| pane.editors.iter().any(EditorState::is_dirty));
+                if !unsaved_changes {
+                    return;
+                }
+
+                prompt = Some(Prompt::new(PromptKind::ConfirmClose, "Close with unsaved changes? (y/n)"));
+                dirty = true;
+            }
+            Event::KeyboardInput(element_state, _scan_code, Some(key)) => {
+                let is_modifier = modifiers.update(element_state, key);
+                if element_state == ElementState::Pressed && !is_modifier {
+                    suppress_character = false;
+
+                    if prompt.is_some() {
+                        // The prompt takes all keyboard input while it's open.
+                        match key {
+                            VirtualKeyCode::Return => {
+                                let finished = prompt.take().unwrap();
+                                match finished.kind {
+                                    PromptKind::SaveAs => {
+                                        if !finished.text.is_empty() {
+                                            let editor = panes.focused_mut().active_editor_mut();
+                                            if let Err(error) = save_editor(&mut xi, editor, finished.text) {
+                                                message = Some(error);
+                                            }
+                                        }
+                                    }
+
+                                    PromptKind::GoToLine => {
+                                        match Location::parse(&*finished.text) {
+                                            Some(location) => {
+                                                let editor = panes.focused_mut().active_editor_mut();
+                                                go_to_location(&mut xi, editor, location);
+                                            }
+                                            None => message = Some(format!("Invalid line number {:?}", finished.text)),
+                                        }
+                                    }
+
+                                    PromptKind::ConfirmClose => {
+                                        if finished.is_yes() {
+                                            return;
+                                        }
+                                    }
+
+                                    PromptKind::ConfirmCloseTab => {
+                                        if finished.is_yes() && !close_focused_tab(&mut xi, &mut panes) {
+                                            return;
+                                        }
+                                    }
+                                }
+                            }
+                            VirtualKeyCode::Escape => prompt = None,
+                            VirtualKeyCode::Back => prompt.as_mut().unwrap().delete_backward(),
+                            _ => {}
+                        }
+                        dirty = true;
+                    } else if find_panel.is_open {
+                        // The find panel takes all keyboard input while it's open.
+                        let editor = panes.focused_mut().active_editor_mut();
+                        let mut search_changed = false;
+                        match key {
+                            VirtualKeyCode::Return => {
+                                send_find(&mut xi, editor, &find_panel);
+                                match find_panel.focused_field {
+                                    FindField::Query if modifiers.shift => send_edit(&mut xi, editor, EditCommand::FindPrevious),
+                                    FindField::Query => send_edit(&mut xi, editor, EditCommand::FindNext),
+                                    FindField::Replacement => {
+                                        send_edit(&mut xi, editor, EditCommand::Replace(&*find_panel.replacement));
+                                        send_edit(&mut xi, editor, EditCommand::ReplaceNext);
+                                    }
+                                }
+                            }
+                            VirtualKeyCode::Escape => {
+                                find_panel.close();
+
+                                // Clear the highlighted matches.
+                                send_edit(&mut xi, editor, EditCommand::Find {
+                                    chars: "",
+                                    case_sensitive: false,
+                                    regex: false,
+                                    whole_words: false,
+                                });
+                            }
+                            VirtualKeyCode::Tab => find_panel.switch_field(),
+                            VirtualKeyCode::Back => {
+                                find_panel.delete_backward();
+                                search_changed = find_panel.focused_field == FindField::Query;
+                            }
+
+                            // Toggles are typed with Alt held, so they shouldn't also type a
+                            // character into the panel.
+                            VirtualKeyCode::C if modifiers.alt => {
+                                find_panel.case_sensitive = !find_panel.case_sensitive;
+                                search_changed = true;
+                                suppress_character = true;
+                            }
+                            VirtualKeyCode::R if modifiers.alt => {
+                                find_panel.regex = !find_panel.regex;
+                                search_changed = true;
+                                suppress_character = true;
+                            }
+                            VirtualKeyCode::W if modifiers.alt => {
+                                find_panel.whole_words = !find_panel.whole_words;
+                                search_changed = true;
+                                suppress_character = true;
+                            }
+                            VirtualKeyCode::A if modifiers.alt && find_panel.is_replacing => {
+                                send_find(&mut xi, editor, &find_panel);
+                                send_edit(&mut xi, editor, EditCommand::Replace(&*find_panel.replacement));
+                                send_edit(&mut xi, editor, EditCommand::ReplaceAll);
+                                suppress_character = true;
+                            }
+                            _ => {}
+                        }
+
+                        if search_changed {
+                            send_find(&mut xi, editor, &find_panel);
+                        }
+                        dirty = true;
+                    } else {
+                        pending_keys.push(KeyChord::new(modifiers, key));
+                        let command = match config.keymap.lookup(&*pending_keys) {
+                            Binding::Command(command) => Some(command),
+                            Binding::Prefix => None,
+                            Binding::Unbound => {
+                                // Don't type the key that ended an unbound sequence, since it was
+                                // meant as part of the sequence rather than as text.
+                                suppress_character = pending_keys.len() > 1;
+                                pending_keys.clear();
+                                None
+                            }
+                        };
+
+                        // Keys that are part of a binding shouldn't also be typed into the document.
+                        if !pending_keys.is_empty() {
+                            suppress_character = true;
+                        }
+
+                        if let Some(command) = command {
+                            pending_keys.clear();
+
+                            if let Some(edit_command) = command.edit_command() {
+                                send_edit(&mut xi, panes.focused_mut().active_editor_mut(), edit_command);
+                            }
+
+                            match command {
+                                // Save the current tab, asking for a path if the document is
+                                // untitled.
+                                Command::Save | Command::SaveAs => {
+                                    let file_path = panes.focused().active_editor().file_path.clone();
+                                    match (file_path, command) {
+                                        (Some(file_path), Command::Save) => {
+                                            let editor = panes.focused_mut().active_editor_mut();
+                                            if let Err(error) = save_editor(&mut xi, editor, file_path) {
+                                                message = Some(error);
+                                            }
+                                        }
+                                        (file_path, _) => {
+                                            let file_path = file_path.unwrap_or_default();
+                                            prompt = Some(Prompt::with_text(PromptKind::SaveAs, "Save as:", &*file_path));
+                                        }
+                                    }
+                                }
+
+                                // Open a new, empty tab in the focused pane.
+                                Command::NewTab => {
+                                    match open_editor(&mut xi, None, window_height) {
+                                        Ok(editor) => panes.focused_mut().add_editor(editor),
+                                        Err(error) => message = Some(error),
+                                    }
+                                }
+
+                                // Close the current tab, giving the user a chance to save their
+                                // work first. Closing the last tab in a pane closes the pane, and
+                                // closing the last pane closes the window.
+                                Command::CloseTab => {
+                                    if panes.focused().active_editor().is_dirty() {
+                                        prompt = Some(Prompt::new(
+                                            PromptKind::ConfirmCloseTab,
+                                            "Close tab with unsaved changes? (y/n)",
+                                        ));
+                                    } else if !close_focused_tab(&mut xi, &mut panes) {
+                                        return;
+                                    }
+                                }
+
+                                Command::NextTab => panes.focused_mut().next_editor(),
+                                Command::PreviousTab => panes.focused_mut().previous_editor(),
+
+                                // Split the focused pane, opening the current file in a new tab so
+                                // that the new pane can be scrolled independently. The new tab
+                                // loads the file from disk, so unsaved changes wouldn't show up in
+                                // it. Rather than showing two different versions of the document,
+                                // it has to be saved before it can be split. The two copies can
+                                // still drift apart afterwards, see `Panes::mark_stale_copies()`.
+                                Command::SplitVertical | Command::SplitHorizontal => {
+                                    let direction = if command == Command::SplitVertical {
+                                        SplitDirection::Vertical
+                                    } else {
+                                        SplitDirection::Horizontal
+                                    };
+
+                                    if panes.focused().active_editor().is_dirty() {
+                                        let title = panes.focused().active_editor().title();
+                                        message = Some(format!("Save {} before splitting it", title));
+                                    } else {
+                                        let file_path = panes.focused().active_editor().file_path.clone();
+                                        match open_editor(&mut xi, file_path.as_ref().map(|path| &**path), window_height) {
+                                            Ok(editor) => panes.split_focused(direction, Pane::new(editor)),
+                                            Err(error) => message = Some(error),
+                                        }
+                                    }
+                                }
+
+                                Command::FocusNextPane => panes.focus_next(),
+                                Command::FocusPreviousPane => panes.focus_previous(),
+
+                                // Smart home: Move to the first non-whitespace character in the
+                                // line, or to the start of the line if the cursor is already there.
+                                // Moving to the indent is done with a click, which extends the
+                                // selection the same way as Shift+clicking when selecting.
+                                Command::MoveToLeftEndOfLine | Command::MoveToLeftEndOfLineAndModifySelection => {
+                                    let extend_selection = command == Command::MoveToLeftEndOfLineAndModifySelection;
+
+                                    let editor = panes.focused_mut().active_editor_mut();
+                                    let indent = match editor.primary_cursor() {
+                                        Some((line_number, line, col)) if col != line.indent() => {
+                                            Some((line_number, line.indent()))
+                                        }
+                                        _ => None,
+                                    };
+
+                                    match indent {
+                                        Some((line, indent)) => {
+                                            send_edit(&mut xi, editor, EditCommand::Click {
+                                                line: line,
+                                                col: indent,
+                                                extend_selection: extend_selection,
+                                                click_count: 1,
+                                            });
+                                        }
+                                        None if extend_selection => {
+                                            send_edit(&mut xi, editor, EditCommand::MoveToLeftEndOfLineAndModifySelection);
+                                        }
+                                        None => send_edit(&mut xi, editor, EditCommand::MoveToLeftEndOfLine),
+                                    }
+                                }
+
+                                // Copying and cutting are requests, so the text is put on the
+                                // clipboard once xi-core responds with it.
+                                Command::Copy | Command::Cut => {
+                                    let tab = panes.focused().active_editor().tab.clone();
+                                    let response = if command == Command::Copy {
+                                        xi.copy(&*tab)
+                                    } else {
+                                        xi.cut(&*tab)
+                                    };
+                                    let cut_from = if command == Command::Cut { Some(tab) } else { None };
+
+                                    pending_copies.push(PendingCopy {
+                                        response: response,
+                                        selection: Selection::Clipboard,
+                                        cut_from: cut_from,
+                                    });
+                                }
+
+                                Command::Paste => {
+                                    if let Some(text) = clipboard.get(Selection::Clipboard) {
+                                        let editor = panes.focused_mut().active_editor_mut();
+                                        send_edit(&mut xi, editor, EditCommand::Insert(&*text));
+                                    }
+                                }
+
+                                Command::GoToLine => {
+                                    prompt = Some(Prompt::new(PromptKind::GoToLine, "Go to line:"));
+                                }
+
+                                // Opening the panel highlights the matches of the last search again.
+                                Command::Find | Command::Replace => {
+                                    find_panel.open(command == Command::Replace);
+                                    send_find(&mut xi, panes.focused_mut().active_editor_mut(), &find_panel);
+                                }
+
+                                // Searching again opens the panel to show the search being used.
+                                Command::FindNext | Command::FindPrevious => {
+                                    if !find_panel.query.is_empty() {
+                                        if !find_panel.is_open {
+                                            let is_replacing = find_panel.is_replacing;
+                                            find_panel.open(is_replacing);
+                                        }
+
+                                        let edit_command = if command == Command::FindNext {
+                                            EditCommand::FindNext
+                                        } else {
+                                            EditCommand::FindPrevious
+                                        };
+
+                                        let editor = panes.focused_mut().active_editor_mut();
+                                        send_find(&mut xi, editor, &find_panel);
+                                        send_edit(&mut xi, editor, edit_command);
+                                    }
+                                }
+
+                                // xi-core moves the cursor by a page, which was sent above. Scroll
+                                // the view along with it so that the cursor stays in the same place
+                                // on screen.
+                                Command::PageUp
+                                | Command::PageDown
+                                | Command::PageUpAndModifySelection
+                                | Command::PageDownAndModifySelection => {
+                                    let up = command == Command::PageUp || command == Command::PageUpAndModifySelection;
+
+                                    let editor = panes.focused_mut().active_editor_mut();
+                                    let page_lines = cmp::max(editor.visible_line_count(), 1);
+                                    let page_height = page_lines as f32 * FONT_SIZE_PX * LINE_HEIGHT;
+                                    let scroll_target = if up {
+                                        editor.scroll_target() - page_height
+                                    } else {
+                                        editor.scroll_target() + page_height
+                                    };
+                                    editor.animate_scroll_to(scroll_target, &config);
+                                }
+
+                                // Everything else is an edit command, which was sent above.
+                                _ => {}
+                            }
+                            dirty = true;
+                        }
+                    }
+                }
+            }
+            Event::MouseMoved(x, y) => {
+                mouse_position = LayoutPoint::new(x as f32, y as f32);
+
+                // Extend the selection in the pane where the drag started, even if the mouse has
+                // moved outside of it.
+                if let Some(pane_index) = dragging_pane {
+                    let pane_bounds = panes.layout(pane_area(window_bounds(window_width, window_height)));
+                    if let (Some(bounds), Some(pane)) = (pane_bounds.get(pane_index), panes.pane(pane_index)) {
+                        let editor = pane.active_editor();
+                        let (line, col) = hit_test(&font, editor, editor_position(*bounds, mouse_position));
+                        xi.edit(&*editor.tab, EditCommand::Drag { line: line, col: col });
+                    }
+                }
+            }
+            // A prompt applies to the editor that was focused when it was opened, so clicks that
+            // could move focus to another pane are ignored until it's closed.
+            Event::MouseInput(ElementState::Pressed, MouseButton::Left) if prompt.is_none() => {
+                let pane_bounds = panes.layout(pane_area(window_bounds(window_width, window_height)));
+                if let Some(pane_index) = pane_bounds.iter().position(|bounds| bounds.contains(&mouse_position)) {
+                    panes.focus(pane_index);
+
+                    // Clicking the tab bar only focuses the pane.
+                    let position = editor_position(pane_bounds[pane_index], mouse_position);
+                    if position.y >= 0.0 {
+                        // Count clicks in quick succession in the same place, cycling back to a
+                        // single click after a triple click.
+                        let now = Instant::now();
+                        click_count = match last_click {
+                            Some((last_time, last_position))
+                                if now.duration_since(last_time) < Duration::from_millis(MULTI_CLICK_INTERVAL_MS)
+                                && (last_position.x - mouse_position.x).abs() <= MULTI_CLICK_DISTANCE
+                                && (last_position.y - mouse_position.y).abs() <= MULTI_CLICK_DISTANCE => {
+                                click_count % 3 + 1
+                            }
+                            _ => 1,
                         };
+                        last_click = Some((now, mouse_position));
+
+                        let editor = panes.focused().active_editor();
+                        let (line, col) = hit_test(&font, editor, position);
+                        xi.edit(&*editor.tab, EditCommand::Click {
+                            line: line,
+                            col: col,
+                            extend_selection: modifiers.shift,
+                            click_count: click_count,
+                        });
+                        dragging_pane = Some(pane_index);
+                    }
+                    dirty = true;
+                }
+            }
+            Event::MouseWheel(delta, phase) => {
+                // Scroll the pane under the mouse, which isn't necessarily the focused one.
+                let pane_bounds = panes.layout(pane_area(window_bounds(window_width, window_height)));
+                if let Some(pane_index) = pane_bounds.iter().position(|bounds| bounds.contains(&mouse_position)) {
+                    if let Some(pane) = panes.pane_mut(pane_index) {
+                        let editor = pane.active_editor_mut();
+                        match delta {
+                            // Wheels scroll by a number of lines per step, which is animated so
+                            // that it doesn't jump.
+                            MouseScrollDelta::LineDelta(_, lines) => {
+                                let delta_pixels = lines * SCROLL_LINES_PER_STEP * FONT_SIZE_PX * LINE_HEIGHT;
+                                let scroll_target = editor.scroll_target() - delta_pixels;
+                                editor.animate_scroll_to(scroll_target, &config);
+                            }
 
-                        if let Some(message) = message {
-                            writeln!(xi_stdin, "{}", message).expect("Failed to send message to xi-core");
+                            // Trackpads already scroll smoothly, so the view follows them exactly.
+                            // Keep track of how fast the view is moving so that it can keep going
+                            // if the trackpad is flung.
+                            MouseScrollDelta::PixelDelta(_, pixels) => {
+                                let now = Instant::now();
+                                let scroll_offset = editor.scroll_offset_pixels - pixels;
+                                scroll_editor(&mut xi, editor, scroll_offset);
+                                trackpad_velocity.add_sample(-pixels, now);
+
+                                if phase == TouchPhase::Ended {
+                                    editor.start_momentum(trackpad_velocity.velocity(now), now);
+                                    trackpad_velocity.reset();
+                                }
+                            }
+                        }
+                    }
+                    scrolled = true;
+                }
+            }
+            Event::MouseInput(ElementState::Released, MouseButton::Left) => {
+                // Text selected with the mouse becomes the primary selection.
+                if let Some(pane) = dragging_pane.take().and_then(|pane_index| panes.pane(pane_index)) {
+                    pending_copies.push(PendingCopy {
+                        response: xi.copy(&*pane.active_editor().tab),
+                        selection: Selection::Primary,
+                        cut_from: None,
+                    });
+                }
+            }
+            Event::MouseInput(ElementState::Pressed, MouseButton::Middle) if prompt.is_none() => {
+                // Paste the primary selection where the mouse was clicked, as is the convention on
+                // X11.
+                let pane_bounds = panes.layout(pane_area(window_bounds(window_width, window_height)));
+                if let Some(pane_index) = pane_bounds.iter().position(|bounds| bounds.contains(&mouse_position)) {
+                    panes.focus(pane_index);
+
+                    let position = editor_position(pane_bounds[pane_index], mouse_position);
+                    if position.y >= 0.0 {
+                        if let Some(text) = clipboard.get(Selection::Primary) {
+                            let editor = panes.focused_mut().active_editor_mut();
+                            let (line, col) = hit_test(&font, editor, position);
+                            xi.edit(&*editor.tab, EditCommand::Click {
+                                line: line,
+                                col: col,
+                                extend_selection: false,
+                                click_count: 1,
+                            });
+                            send_edit(&mut xi, editor, EditCommand::Insert(&*text));
                         }
                     }
+                    dirty = true;
                 }
             }
+            Event::Focused(true) => {
+                window_focused = true;
+                dirty = true;
+            }
+            Event::Focused(false) => {
+                // Any modifiers released while the window didn't have focus won't be reported, so
+                // assume they've all been released.
+                modifiers = Modifiers::default();
+                pending_keys.clear();
+
+                window_focused = false;
+                dirty = true;
+            }
             Event::Resized(new_width, new_height) => {
-                editor.view_width_pixels = new_width as usize;
-                editor.view_height_pixels = new_height as usize;
+                window_width = new_width;
+                window_height = new_height;
                 dirty = true;
             }
             Event::ReceivedCharacter(character) => {
                 // TODO: OS X will send "private usage codepoints" which we want to filter out.
                 // Issue tracker: https://github.com/excaliburHisSheath/text-edit/issues/2
-                if !character.is_control() && !(character >= '\u{e000}' && character <= '\u{f8ff}') {
-                    // Send the character to xi-core.
-                    let message = format!(r#"{{"method":"edit","params":{{"method":"insert","params":{{"chars":"{}"}},"tab":"0"}}}}"#, character);
-                    writeln!(xi_stdin, "{}", message).expect("Failed to send message to xi-core");
+                if suppress_character {
+                    suppress_character = false;
+                } else if !character.is_control() && !(character >= '\u{e000}' && character <= '\u{f8ff}') {
+                    if let Some(ref mut prompt) = prompt {
+                        prompt.insert(character);
+                        dirty = true;
+                    } else if find_panel.is_open {
+                        find_panel.insert(character);
+                        if find_panel.focused_field == FindField::Query {
+                            send_find(&mut xi, panes.focused_mut().active_editor_mut(), &find_panel);
+                        }
+                        dirty = true;
+                    } else {
+                        // Send the character to xi-core.
+                        let editor = panes.focused_mut().active_editor_mut();
+                        send_edit(&mut xi, editor, EditCommand::Insert(&*character.to_string()));
+                    }
                 }
             }
             _ => {},
         }
 
-        let mut scroll_to_line = None;
-
         // Receive messages from xi-core.
-        for message in receiver.try_iter() {
-            // Parse the response string to structured JSON data.
-            let update_value = serde_json::from_str::<Value>(&*message).expect("Failed to parse response json");
-
-            // Look for "update" messages.
-            // TODO: Look for all the other messages xi-core sends.
-            if let Some(line_data) = update_value.search("lines") {
-                editor.lines.clear();
-                for line_contents in line_data.as_array().expect("\"lines\" wasn't an array") {
-                    let line_contents = line_contents.as_array().expect("Line wasn't an array");
-
-                    // TODO: If we're doing visible whitespace we don't want to trim the trailing
-                    // whitespace.
-                    // TODO: We probably want to perform unicode normalization here? Or maybe
-                    // we want to do it when we generate the glyphs?
-                    let line_string = line_contents[0]
-                        .as_str()
-                        .expect("First element of line wasn't a string")
-                        .to_string();
-                    let mut line_stuffffff = LineContents {
-                        text: line_string,
-                        cursors: Vec::new(),
-                        selections: Vec::new(),
+        for notification in notifications.try_iter() {
+            match notification {
+                Notification::Update(update) => {
+                    // Find the editor displaying the updated tab.
+                    let editor = match panes.find_editor_mut(&*update.tab) {
+                        Some(editor) => editor,
+                        None => continue,
                     };
 
-                    for line_control in &line_contents[1..] {
-                        let line_control = line_control.as_array().expect("Line control wasn't an array");
-                        let control_type = line_control[0].as_str().expect("First element of line control was not a string");
-                        match control_type {
-                            "cursor" => {
-                                let col = line_control[1].as_u64().expect("Cursor index wasn't an integer");
+                    if let Some(lines) = update.lines {
+                        editor.lines = lines.into_iter().map(LineContents::from).collect();
+                    }
 
-                                // Xi internally represents cursor position as a `usize` so this cast
-                                // shouldn't overflow.
-                                line_stuffffff.cursors.push(col as usize);
-                            }
-                            "sel" => {
-                                let start = line_control[1].as_u64().expect("Selection start wasn't an integer");
-                                let end = line_control[2].as_u64().expect("Selection end wasn't an integer");
+                    if let Some(first_line) = update.first_line {
+                        editor.first_line = first_line;
+                    }
 
-                                // Xi internally represents cursor position as a `usize` so these
-                                // casts shouldn't overflow.
-                                line_stuffffff.selections.push((start as usize, end as usize));
-                            }
-                            "fg" => { unimplemented!() }
-                            _ => panic!("Unknown control type: {:?}", control_type),
-                        }
+                    if let Some(height) = update.height {
+                        editor.height_in_lines = height;
+                    }
+
+                    // xi-core scrolls to the primary cursor whenever it moves, so that's also where
+                    // the primary cursor is.
+                    if let Some((line, col)) = update.scroll_to {
+                        editor.scroll_to_line = Some(line);
+                        editor.cursor_position = (line, col);
                     }
-                    editor.lines.push(line_stuffffff);
                 }
             }
 
-            if let Some(first_line) = update_value.search("first_line") {
-                editor.first_line = first_line.as_u64().expect("\"first_line\" wasn't a number") as usize;
+            dirty = true;
+        }
+
+        // Check for saves that xi-core has finished.
+        let mut saved_editors = Vec::new();
+        for pane in panes.panes_mut() {
+            for editor in &mut pane.editors {
+                match editor.poll_save() {
+                    Some(Ok(())) => {
+                        saved_editors.push((editor.tab.clone(), editor.file_path.clone().unwrap()));
+                        dirty = true;
+                    }
+                    Some(Err(error)) => {
+                        message = Some(error);
+                        dirty = true;
+                    }
+                    None => {}
+                }
             }
+        }
+
+        // Other copies of a saved file no longer match what's on disk.
+        for (tab, file_path) in saved_editors {
+            panes.mark_stale_copies(&*tab, &*file_path);
+        }
+
+        // Put text that xi-core has copied or cut onto the clipboard.
+        let mut still_pending = Vec::new();
+        for pending_copy in pending_copies.drain(..) {
+            match pending_copy.response.try_recv() {
+                // Copying with nothing selected leaves the clipboard as it was.
+                Ok(Ok(Value::String(ref text))) if text.is_empty() => {}
+                Ok(Ok(Value::String(text))) => {
+                    if let Some(editor) = pending_copy.cut_from.and_then(|tab| panes.find_editor_mut(&*tab)) {
+                        editor.record_change();
+                        dirty = true;
+                    }
+                    clipboard.set(pending_copy.selection, text);
+                }
+                Ok(Ok(_)) => {}
+                Ok(Err(error)) => {
+                    message = Some(format!("Failed to copy selection: {}", error));
+                    dirty = true;
+                }
+                Err(TryRecvError::Empty) => still_pending.push(pending_copy),
 
-            // Look for "scrollto" in the message.
-            if let Some(scrollto) = update_value.search("scrollto") {
-                let scrollto = scrollto.as_array().expect("\"scrollto\" was not an array");
-                let line = scrollto[0].as_u64().expect("\"scrollto\" element wasn't an integer");
-                scroll_to_line = Some(line as usize);
+                // xi-core has gone away, so the copy is never going to finish.
+                Err(TryRecvError::Disconnected) => {}
+            }
+        }
+        pending_copies = still_pending;
+
+        // Advance any smooth scrolling.
+        let now = Instant::now();
+        for pane in panes.panes_mut() {
+            for editor in &mut pane.editors {
+                if editor.update_scroll(now) {
+                    scrolled = true;
+                }
             }
+        }
 
+        // Redraw whenever the blinking cursor is shown or hidden. An unfocused cursor doesn't blink.
+        let cursor_visible = !window_focused || cursor_blink.is_visible(now);
+        if cursor_visible != drawn_cursor_visible {
             dirty = true;
         }
 
+        // Keep the window title in sync with the focused editor, e.g. when switching tabs, when the
+        // document has unsaved changes, or when it's saved under a new name.
+        let title = window_title_for_editor(panes.focused().active_editor());
+        if title != window_title {
+            window.set_title(&*title);
+            window_title = title;
+        }
+
         if dirty {
             dirty = false;
 
@@ -290,42 +947,287 @@ fn main() {
                 pipeline_id,
                 font_key,
                 &font,
-                &mut editor,
-                scroll_to_line,
+                &config,
+                &mut panes,
+                window_focused,
+                cursor_visible,
+                &find_panel,
+                prompt.as_ref(),
+                message.as_ref().map(|message| &**message),
+                LayoutSize::new(window_width as f32, window_height as f32),
             );
             api.set_root_display_list(
                 Some(root_background_color),
                 epoch,
-                LayoutSize::new(editor.view_width_pixels as f32, editor.view_height_pixels as f32),
+                LayoutSize::new(window_width as f32, window_height as f32),
                 builder,
             );
             api.generate_frame();
+
+            drawn_cursor_visible = cursor_visible;
+
+            // Building the display list may have scrolled to the cursor, and the new scroll layers
+            // need to be moved to the editors' scroll offsets.
+            scrolled = true;
+        }
+
+        // Scrolling only moves the existing scroll layers, so it doesn't need a new display list.
+        if scrolled {
+            scrolled = false;
+
+            scroll_layers(&api, pipeline_id, &panes);
+
+            // Let xi-core know about any changes to which lines are visible, e.g. from scrolling
+            // or resizing the window.
+            for pane in panes.panes_mut() {
+                for editor in &mut pane.editors {
+                    sync_visible_range(&mut xi, editor);
+                }
+            }
+        }
+
+        // Wake up in time to show or hide the cursor again, unless we're already going to.
+        if window_focused {
+            let next_blink = cursor_blink.next_change(now);
+            if next_blink.is_some() && next_blink != blink_wakeup {
+                timer.wake_at(next_blink.unwrap());
+            }
+            blink_wakeup = next_blink;
+        }
+
+        // Keep drawing frames until all scrolling has finished.
+        if panes.panes().iter().any(|pane| pane.editors.iter().any(EditorState::is_scrolling)) {
+            animation_proxy.wakeup_event_loop();
         }
 
         renderer.update();
-        renderer.render(DeviceUintSize::new(editor.view_width_pixels as u32, editor.view_height_pixels as u32) * hidpi_factor as u32);
+        renderer.render(DeviceUintSize::new(window_width, window_height) * hidpi_factor as u32);
 
         window.swap_buffers().ok();
     }
 }
 
+/// Opens a new tab in xi-core and creates an editor to display it.
+///
+/// If `file_path` is given the file is loaded into the new tab, otherwise the tab starts out as an
+/// empty, untitled document.
+///
+/// The editor's view size is set when its pane is laid out, so until then xi-core is told that the
+/// editor fills the height of the window.
+///
+/// Returns a message for the user if xi-core couldn't open a tab, e.g. because it has exited or
+/// stopped responding.
+fn open_editor(xi: &mut XiClient, file_path: Option<&str>, window_height: u32) -> std::result::Result<EditorState, String> {
+    // Open a tab and wait for xi-core to tell us its name. This blocks the UI, so don't wait forever
+    // if xi-core is stuck.
+    let tab = match xi.new_tab().recv_timeout(Duration::from_millis(XI_RESPONSE_TIMEOUT_MS)) {
+        Ok(Ok(tab)) => tab,
+        Ok(Err(error)) => return Err(format!("xi-core failed to create a new tab: {}", error)),
+        Err(RecvTimeoutError::Timeout) => return Err("xi-core didn't respond to opening a new tab".into()),
+        Err(RecvTimeoutError::Disconnected) => return Err("Lost connection to xi-core".into()),
+    };
+    let tab = match tab.as_str() {
+        Some(tab) => tab.to_string(),
+        None => return Err(format!("Name of new tab wasn't a string: {}", tab)),
+    };
+
+    let view_height_in_lines = window_height as f32 / (FONT_SIZE_PX * LINE_HEIGHT);
+    let mut editor = EditorState::new(tab, file_path.map(String::from), view_height_in_lines as usize);
+    if let Some(file_path) = file_path {
+        editor.line_ending = detect_line_ending(file_path);
+    }
+
+    // Change the visible region of the file (no response).
+    let (first_line, last_line) = editor.scrolled_range;
+    xi.edit(&*editor.tab, EditCommand::Scroll(first_line, last_line));
+
+    // Open this file and get the lines from the file.
+    if let Some(file_path) = file_path {
+        // TODO: Windows-style path separators cause this to fail.
+        // Issue tracker: https://github.com/excaliburHisSheath/text-edit/issues/5
+        xi.edit(&*editor.tab, EditCommand::Open(file_path));
+    }
+
+    Ok(editor)
+}
+
+/// Returns the name of the line ending used by the file at `file_path`, judging by its first line.
+///
+/// Files that don't exist yet or only have a single line use "LF", which is what xi-core inserts.
+fn detect_line_ending(file_path: &str) -> &'static str {
+    let mut first_line = Vec::new();
+    let file = match File::open(file_path) {
+        Ok(file) => file,
+        Err(_) => return "LF",
+    };
+
+    match BufReader::new(file).read_until(b'\n', &mut first_line) {
+        Ok(_) if first_line.ends_with(b"\r\n") => "CRLF",
+        _ => "LF",
+    }
+}
+
+/// Returns the directory containing the file at `file_path` for display, shortened to start with
+/// `~` if it's within the home directory.
+fn display_directory(file_path: &str) -> Option<String> {
+    let parent = match Path::new(file_path).parent() {
+        Some(parent) => parent,
+        None => return None,
+    };
+
+    // Relative paths are relative to the directory the editor was started in.
+    let directory = match env::current_dir() {
+        Ok(ref current_dir) if parent.as_os_str().is_empty() => current_dir.clone(),
+        Ok(current_dir) => current_dir.join(parent),
+        Err(_) => parent.to_path_buf(),
+    };
+
+    if let Some(home_dir) = env::home_dir() {
+        if let Ok(relative) = directory.strip_prefix(&home_dir) {
+            if relative.as_os_str().is_empty() {
+                return Some("~".into());
+            }
+            return Some(Path::new("~").join(relative).display().to_string());
+        }
+    }
+
+    Some(directory.display().to_string())
+}
+
+/// Closes the active tab of the focused pane, closing the pane as well if it was the pane's last
+/// tab.
+///
+/// Returns `false` if the last pane was closed, in which case the window should close too.
+fn close_focused_tab(xi: &mut XiClient, panes: &mut Panes) -> bool {
+    let editor = panes.focused_mut().close_active_editor();
+    xi.delete_tab(&*editor.tab);
+
+    !panes.focused().editors.is_empty() || panes.close_focused().is_some()
+}
+
+/// Moves the cursor in an editor to `location`, scrolling its line to the middle of the view.
+fn go_to_location(xi: &mut XiClient, editor: &mut EditorState, location: Location) {
+    // Locations count from 1, but xi-core counts from 0. Past the end of the document the cursor
+    // goes to the last line instead, if we know how long the document is yet.
+    let line = location.line - 1;
+    let line = if editor.height_in_lines > 0 {
+        cmp::min(line, editor.height_in_lines - 1)
+    } else {
+        line
+    };
+
+    xi.edit(&*editor.tab, EditCommand::Click {
+        line: line,
+        col: location.column - 1,
+        extend_selection: false,
+        click_count: 1,
+    });
+
+    editor.scroll_to_line = Some(line);
+    editor.center_scroll_to_line = true;
+}
+
+/// Sends the find panel's search to an editor's tab, so that xi-core highlights its matches.
+fn send_find(xi: &mut XiClient, editor: &mut EditorState, find_panel: &FindPanel) {
+    send_edit(xi, editor, EditCommand::Find {
+        chars: &*find_panel.query,
+        case_sensitive: find_panel.case_sensitive,
+        regex: find_panel.regex,
+        whole_words: find_panel.whole_words,
+    });
+}
+
+/// Sends an edit command to an editor's tab, keeping track of whether it changes the document.
+fn send_edit(xi: &mut XiClient, editor: &mut EditorState, command: EditCommand) {
+    xi.edit(&*editor.tab, command);
+    editor.record_edit(command);
+}
+
+/// Scrolls an editor's view so that its top is `scroll_offset` pixels from the top of the document,
+/// telling xi-core which lines are now visible.
+fn scroll_editor(xi: &mut XiClient, editor: &mut EditorState, scroll_offset: f32) {
+    editor.stop_scrolling();
+    editor.scroll_offset_pixels = scroll_offset;
+    editor.clamp_scroll_offset();
+    sync_visible_range(xi, editor);
+}
+
+/// Tells xi-core which lines are visible in an editor, if they've changed since it was last told.
+///
+/// xi-core only sends the lines in this range, so it needs to be kept up to date whenever the
+/// editor is scrolled or resized.
+fn sync_visible_range(xi: &mut XiClient, editor: &mut EditorState) {
+    let visible_range = editor.visible_range();
+    if visible_range != editor.scrolled_range {
+        let (first_line, last_line) = visible_range;
+        xi.edit(&*editor.tab, EditCommand::Scroll(first_line, last_line));
+        editor.scrolled_range = visible_range;
+    }
+}
+
+/// Asks xi-core to save an editor's document to `file_path`.
+///
+/// The editor is marked as saved once xi-core responds, see `EditorState::poll_save()`. Saving a
+/// stale copy of a file over the file is refused, since it would throw away the changes saved from
+/// the other copy.
+fn save_editor(xi: &mut XiClient, editor: &mut EditorState, file_path: String) -> std::result::Result<(), String> {
+    if editor.is_stale && editor.file_path.as_ref() == Some(&file_path) {
+        return Err(format!(
+            "{} was saved from another pane since this copy was opened, use Save As to keep this copy",
+            editor.title()
+        ));
+    }
+
+    let response = xi.save(&*editor.tab, &*file_path);
+    editor.pending_save = Some(PendingSave {
+        response: response,
+        edit_count: editor.edit_count,
+        file_path: file_path,
+    });
+    Ok(())
+}
+
+/// Returns the window title to use while `editor` is focused, e.g.
+/// "main.rs (~/text-edit/src) - text-edit".
+///
+/// The parent directory makes it possible to tell apart files with the same name.
+fn window_title_for_editor(editor: &EditorState) -> String {
+    match editor.parent_directory {
+        Some(ref directory) => format!("{} ({}) - text-edit", tab_label(editor), directory),
+        None => format!("{} - text-edit", tab_label(editor)),
+    }
+}
+
+/// Returns the label for an editor's tab.
+///
+/// Documents with unsaved changes are marked with a dot.
+fn tab_label(editor: &EditorState) -> String {
+    if editor.is_dirty() {
+        format!("\u{25cf} {}", editor.title())
+    } else {
+        editor.title().into()
+    }
+}
+
 fn build_display_lists(
     pipeline_id: PipelineId,
     font_key: FontKey,
     font: &Font,
-    editor: &mut EditorState,
-    scroll_to_line: Option<usize>,
+    config: &Config,
+    panes: &mut Panes,
+    window_focused: bool,
+    cursor_visible: bool,
+    find_panel: &FindPanel,
+    prompt: Option<&Prompt>,
+    message: Option<&str>,
+    window_size: LayoutSize,
 ) -> DisplayListBuilder {
-    let view_width = editor.view_width_pixels as f32;
-    let view_height = editor.view_height_pixels as f32;
-
     let mut builder = DisplayListBuilder::new(pipeline_id);
 
-    let bounds = LayoutRect::new(LayoutPoint::new(0.0, 0.0), LayoutSize::new(view_width, view_height));
+    let bounds = LayoutRect::new(LayoutPoint::new(0.0, 0.0), window_size);
     let clip_region = {
         let complex = webrender_traits::ComplexClipRegion::new(
-            LayoutRect::new(LayoutPoint::new(0.0, 0.0),
-            LayoutSize::new(view_width, view_height)),
+            bounds,
             webrender_traits::BorderRadius::uniform(0.0),
         );
 
@@ -343,99 +1245,604 @@ fn build_display_lists(
         Vec::new(),
     );
 
-    // Sample text to demonstrate text layout and rendering.
-    let em_border = BorderSide {
-        width: 1.0,
-        color: ColorF::new(1.0, 0.0, 1.0, 1.0),
-        style: BorderStyle::Solid,
-    };
-    let glyph_border = BorderSide {
-        width: 1.0,
-        color: ColorF::new(1.0, 0.0, 0.0, 1.0),
-        style: BorderStyle::Solid,
-    };
-    let text_bounds = LayoutRect::new(LayoutPoint::new(0.0, 0.0), LayoutSize::new(view_width, view_height));
-
-    // TODO: Investigate why this scaling is necessary. Rusttype says it takes font scale in pixels,
-    // but glyphs rendered with the system renderer don't match the sizes produced by rusttype
-    // unless we slightly tweak the rusttype scale. I used Atom displaying the Hack-Regular font at
-    // 14px to compare, so if this is actually wrong blame Atom.
-    //
-    // Issue tracker: https://github.com/excaliburHisSheath/text-edit/issues/4
-    let font_scale = Scale::uniform(FONT_SIZE_PX / PIXEL_TO_POINT);
-    let v_metrics = font.v_metrics(font_scale);
-    let line_height = FONT_SIZE_PX * LINE_HEIGHT;
+    // Fill the window with the divider color, the panes are drawn over it leaving gaps between them.
+    builder.push_rect(bounds, clip_region, ColorF::new(0.05, 0.05, 0.05, 1.0));
 
-    if let Some(scroll_to_line) = scroll_to_line {
-        let line_top = scroll_to_line as f32 * line_height;
-        let line_bottom = scroll_to_line as f32 * line_height + line_height;
+    let focused_pane = panes.focused_index();
+    let pane_bounds = panes.layout(pane_area(bounds));
+    for (index, (pane, pane_bounds)) in panes.panes_mut().into_iter().zip(pane_bounds).enumerate() {
+        let focused = window_focused && index == focused_pane;
+        push_pane(&mut builder, font_key, font, config, pane, index, pane_bounds, focused, cursor_visible);
+    }
 
-        let view_top = editor.scroll_offset_pixels as f32 + line_height * 2.0;
-        let view_bottom = editor.scroll_offset_pixels + editor.view_height_pixels as f32 - line_height * 2.0;
+    push_status_bar(&mut builder, font_key, font, panes.focused().active_editor(), message, window_size);
 
-        // TODO: We could use a `clamp()` operation to represent this more clearly, I think?
-        if view_top > line_top {
-            // Scroll view upwards to match line top.
-            editor.scroll_offset_pixels = line_top - line_height * 2.0;
-        } else if view_bottom < line_bottom {
-            // Scroll view downwards to match line bottom.
-            editor.scroll_offset_pixels = line_bottom - editor.view_height_pixels as f32 + line_height * 2.0;
-        }
+    // The find panel and the prompt are drawn over the status bar, with the prompt on top.
+    if find_panel.is_open {
+        push_find_panel(&mut builder, font_key, font, find_panel, window_size);
+    }
 
-        // Now clamp the scroll view so it doesn't go negative.
-        if editor.scroll_offset_pixels < 0.0 {
-            editor.scroll_offset_pixels = 0.0;
-        }
+    if let Some(prompt) = prompt {
+        push_prompt(&mut builder, font_key, font, prompt, window_size);
     }
 
-    // TODO: There seems to be a 5 pixel gap at the top of the window on Windows. Is this something
-    // we're accidentally introducing, or is it created by webrender somehow?
-    let mut origin = Point { x: 0.0, y: editor.first_line as f32 * line_height - editor.scroll_offset_pixels - 5.0 };
+    builder.pop_stacking_context();
 
-    for line in &editor.lines {
-        origin = origin + vector(0.0, line_height);
+    builder
+}
 
-        // Keep track of where the line ends so that we can render a cursor at the end of the line
-        // if necessary.
-        let mut line_end = 0.0;
-        let mut last_index = 0;
+/// Draws the pane at `index`, i.e. its tab bar and its active editor, in its own stacking context.
+fn push_pane(
+    builder: &mut DisplayListBuilder,
+    font_key: FontKey,
+    font: &Font,
+    config: &Config,
+    pane: &mut Pane,
+    index: usize,
+    bounds: LayoutRect,
+    focused: bool,
+    cursor_visible: bool,
+) {
+    // Everything within the stacking context is positioned relative to the pane.
+    let local_bounds = LayoutRect::new(LayoutPoint::new(0.0, 0.0), bounds.size);
+    let clip_region = {
+        let complex = webrender_traits::ComplexClipRegion::new(
+            local_bounds,
+            webrender_traits::BorderRadius::uniform(0.0),
+        );
 
-        let glyphs = font
-            .layout(&*line.text, font_scale, origin)
-            .enumerate()
-            .inspect(|&(index, ref glyph)| {
-                let pos = glyph.position();
-                let scaled = glyph.unpositioned();
-                let h_metrics = scaled.h_metrics();
-
-                // Update line end tracking.
-                line_end = pos.x + h_metrics.advance_width;
-                last_index = index + 1;
-
-                // Draw cursors where appropriate.
-                // ================================================================================
-                // TODO: Is there a more efficient way to do this? E.g. could we sort the list and
-                // pop the cursors as we draw them so we only have to check the next cursor?
-                for cursor_col in &line.cursors {
-                    if *cursor_col == index {
-                        let line_middle = pos.y - v_metrics.ascent - v_metrics.descent + (v_metrics.ascent + v_metrics.descent) / 2.0;
-                        let line_top = line_middle - line_height / 2.0;
-
-                        // Draw a cursor at this col.
-                        builder.push_rect(
-                            LayoutRect::new(
-                                LayoutPoint::new(pos.x, line_top),
-                                LayoutSize::new(1.0, line_height),
-                            ),
-                            clip_region,
-                            ColorF::new(1.0, 1.0, 1.0, 1.0),
-                        );
-                    }
-                }
+        builder.new_clip_region(&local_bounds, vec![complex], None)
+    };
+
+    builder.push_stacking_context(
+        webrender_traits::ScrollPolicy::Scrollable,
+        bounds,
+        clip_region,
+        0,
+        &LayoutTransform::identity(),
+        &LayoutTransform::identity(),
+        webrender_traits::MixBlendMode::Normal,
+        Vec::new(),
+    );
+
+    // The editors fill the pane below the tab bar. All of the pane's editors are resized, not just
+    // the active one, so that they're ready to be drawn when switching tabs.
+    let view_height = (bounds.size.height - TAB_BAR_HEIGHT).max(0.0);
+    for editor in &mut pane.editors {
+        editor.view_width_pixels = bounds.size.width as usize;
+        editor.view_height_pixels = view_height as usize;
+    }
+
+    push_tab_bar(builder, font_key, font, &pane.editors, pane.active_editor, bounds.size.width, focused);
+    push_editor(
+        builder,
+        font_key,
+        font,
+        config,
+        pane.active_editor_mut(),
+        LayoutPoint::new(0.0, TAB_BAR_HEIGHT),
+        scroll_root_id(index),
+        focused,
+        cursor_visible,
+    );
+
+    builder.pop_stacking_context();
+}
+
+/// Draws the tab bar along the top of a pane, with one tab for each of the pane's editors.
+///
+/// The active tab of the focused pane is marked with an accent along its top edge.
+fn push_tab_bar(
+    builder: &mut DisplayListBuilder,
+    font_key: FontKey,
+    font: &Font,
+    editors: &[EditorState],
+    active_editor: usize,
+    width: f32,
+    focused: bool,
+) {
+    let bounds = LayoutRect::new(LayoutPoint::new(0.0, 0.0), LayoutSize::new(width, TAB_BAR_HEIGHT));
+    let clip_region = webrender_traits::ClipRegion::simple(&bounds);
+
+    builder.push_rect(bounds, clip_region, ColorF::new(0.05, 0.05, 0.05, 1.0));
+
+    // TODO: See `push_editor()` for why we're scaling the font.
+    let font_scale = Scale::uniform(FONT_SIZE_PX / PIXEL_TO_POINT);
+    let v_metrics = font.v_metrics(font_scale);
+
+    // Center the labels vertically within the tab bar.
+    let baseline = TAB_BAR_HEIGHT / 2.0 + (v_metrics.ascent + v_metrics.descent) / 2.0;
+
+    let mut tab_left = 0.0;
+    for (index, editor) in editors.iter().enumerate() {
+        // Lay out the label first so we know how wide the tab needs to be.
+        let (glyphs, label_right) = layout_text(
+            font,
+            font_scale,
+            &*tab_label(editor),
+            point(tab_left + TAB_PADDING, baseline),
+        );
+        let tab_right = label_right + TAB_PADDING;
+        let tab_bounds = LayoutRect::new(
+            LayoutPoint::new(tab_left, 0.0),
+            LayoutSize::new(tab_right - tab_left, TAB_BAR_HEIGHT),
+        );
+
+        // The active tab uses the same background as the editor so that they appear connected.
+        let (background_color, text_color) = if index == active_editor {
+            (ColorF::new(0.1, 0.1, 0.1, 1.0), ColorF::new(0.8, 0.8, 0.8, 1.0))
+        } else {
+            (ColorF::new(0.15, 0.15, 0.15, 1.0), ColorF::new(0.5, 0.5, 0.5, 1.0))
+        };
+
+        builder.push_rect(tab_bounds, clip_region, background_color);
+        if focused && index == active_editor {
+            builder.push_rect(
+                LayoutRect::new(tab_bounds.origin, LayoutSize::new(tab_bounds.size.width, 2.0)),
+                clip_region,
+                ColorF::new(0.3, 0.5, 0.9, 1.0),
+            );
+        }
+        builder.push_text(
+            tab_bounds,
+            clip_region,
+            glyphs,
+            font_key,
+            text_color,
+            Au::from_f32_px(FONT_SIZE_PX),
+            Au::from_px(0),
+        );
+
+        // Leave a 1 pixel gap between tabs.
+        tab_left = tab_right + 1.0;
+    }
+}
+
+/// Draws the status bar along the bottom of the window in its own stacking context, showing
+/// information about the focused editor.
+///
+/// A `message` for the user takes the place of the file path.
+fn push_status_bar(
+    builder: &mut DisplayListBuilder,
+    font_key: FontKey,
+    font: &Font,
+    editor: &EditorState,
+    message: Option<&str>,
+    window_size: LayoutSize,
+) {
+    let bounds = LayoutRect::new(
+        LayoutPoint::new(0.0, window_size.height - STATUS_BAR_HEIGHT),
+        LayoutSize::new(window_size.width, STATUS_BAR_HEIGHT),
+    );
+
+    // Everything within the stacking context is positioned relative to the status bar.
+    let local_bounds = LayoutRect::new(LayoutPoint::new(0.0, 0.0), bounds.size);
+    let clip_region = {
+        let complex = webrender_traits::ComplexClipRegion::new(
+            local_bounds,
+            webrender_traits::BorderRadius::uniform(0.0),
+        );
+
+        builder.new_clip_region(&local_bounds, vec![complex], None)
+    };
+
+    builder.push_stacking_context(
+        webrender_traits::ScrollPolicy::Scrollable,
+        bounds,
+        clip_region,
+        0,
+        &LayoutTransform::identity(),
+        &LayoutTransform::identity(),
+        webrender_traits::MixBlendMode::Normal,
+        Vec::new(),
+    );
+
+    builder.push_rect(local_bounds, clip_region, ColorF::new(0.05, 0.05, 0.05, 1.0));
+
+    // TODO: See `push_editor()` for why we're scaling the font.
+    let font_scale = Scale::uniform(FONT_SIZE_PX / PIXEL_TO_POINT);
+    let v_metrics = font.v_metrics(font_scale);
+    let baseline = STATUS_BAR_HEIGHT / 2.0 + (v_metrics.ascent + v_metrics.descent) / 2.0;
+
+    // The file goes on the left, marked with a dot when it has unsaved changes like in the tab bar.
+    let file_path = match editor.file_path {
+        Some(ref file_path) => &**file_path,
+        None => "untitled",
+    };
+    let (file_label, file_color) = match message {
+        Some(message) => (message.to_string(), ColorF::new(0.9, 0.5, 0.4, 1.0)),
+        None if editor.is_dirty() => (format!("\u{25cf} {}", file_path), ColorF::new(0.8, 0.8, 0.8, 1.0)),
+        None => (file_path.to_string(), ColorF::new(0.8, 0.8, 0.8, 1.0)),
+    };
+    let (file_glyphs, _) = layout_text(font, font_scale, &*file_label, point(TAB_PADDING, baseline));
+    builder.push_text(
+        local_bounds,
+        clip_region,
+        file_glyphs,
+        font_key,
+        file_color,
+        Au::from_f32_px(FONT_SIZE_PX),
+        Au::from_px(0),
+    );
+
+    // Everything else goes on the right. xi-core only works with UTF-8, so that's always the
+    // encoding.
+    let mut details = Vec::new();
+    let (line, col) = editor.cursor_position;
+    details.push(format!("Ln {}, Col {}", line + 1, col + 1));
+
+    // Selections can only be counted in the lines xi-core has sent, so say so when that isn't the
+    // whole document.
+    let selections = editor.visible_selection_count();
+    if selections > 0 {
+        let label = if selections == 1 { "selection" } else { "selections" };
+        if editor.has_all_lines() {
+            details.push(format!("{} {}", selections, label));
+        } else {
+            details.push(format!("{} {} in view", selections, label));
+        }
+    }
+
+    // Show how far the document is from what's saved, which is how far undoing would need to go.
+    match editor.unsaved_edit_count() {
+        0 => {}
+        1 => details.push("1 unsaved edit".to_string()),
+        edits => details.push(format!("{} unsaved edits", edits)),
+    }
+
+    details.push(editor.line_ending.to_string());
+    details.push("UTF-8".to_string());
+
+    let details = details.join("    ");
+    let details_width = layout_text(font, font_scale, &*details, point(0.0, 0.0)).1;
+    let details_left = window_size.width - TAB_PADDING - details_width;
+    let (details_glyphs, _) = layout_text(font, font_scale, &*details, point(details_left, baseline));
+    builder.push_text(
+        local_bounds,
+        clip_region,
+        details_glyphs,
+        font_key,
+        ColorF::new(0.5, 0.5, 0.5, 1.0),
+        Au::from_f32_px(FONT_SIZE_PX),
+        Au::from_px(0),
+    );
+
+    builder.pop_stacking_context();
+}
+
+/// Draws the find panel along the bottom of the window, with a row for each of its fields.
+fn push_find_panel(
+    builder: &mut DisplayListBuilder,
+    font_key: FontKey,
+    font: &Font,
+    find_panel: &FindPanel,
+    window_size: LayoutSize,
+) {
+    let rows = if find_panel.is_replacing { 2.0 } else { 1.0 };
+    let height = FIND_PANEL_ROW_HEIGHT * rows;
+    let bounds = LayoutRect::new(
+        LayoutPoint::new(0.0, window_size.height - height),
+        LayoutSize::new(window_size.width, height),
+    );
+    let clip_region = webrender_traits::ClipRegion::simple(&bounds);
+
+    builder.push_rect(bounds, clip_region, ColorF::new(0.15, 0.15, 0.15, 1.0));
+
+    // TODO: See `push_editor()` for why we're scaling the font.
+    let font_scale = Scale::uniform(FONT_SIZE_PX / PIXEL_TO_POINT);
+    let v_metrics = font.v_metrics(font_scale);
+    let row_baseline = |row: f32| {
+        bounds.origin.y + FIND_PANEL_ROW_HEIGHT * row + FIND_PANEL_ROW_HEIGHT / 2.0
+            + (v_metrics.ascent + v_metrics.descent) / 2.0
+    };
+
+    let mut fields = vec![("Find:", &*find_panel.query, FindField::Query)];
+    if find_panel.is_replacing {
+        fields.push(("Replace:", &*find_panel.replacement, FindField::Replacement));
+    }
+
+    // Line the fields up after the longest label.
+    let text_left = fields
+        .iter()
+        .map(|&(label, _, _)| layout_text(font, font_scale, label, point(TAB_PADDING, 0.0)).1)
+        .fold(0.0, f32::max) + TAB_PADDING / 2.0;
+
+    for (row, &(label, text, field)) in fields.iter().enumerate() {
+        let baseline = row_baseline(row as f32);
+        let (label_glyphs, _) = layout_text(font, font_scale, label, point(TAB_PADDING, baseline));
+        let (text_glyphs, text_right) = layout_text(font, font_scale, text, point(text_left, baseline));
+
+        builder.push_text(
+            bounds,
+            clip_region,
+            label_glyphs,
+            font_key,
+            ColorF::new(0.5, 0.5, 0.5, 1.0),
+            Au::from_f32_px(FONT_SIZE_PX),
+            Au::from_px(0),
+        );
+        builder.push_text(
+            bounds,
+            clip_region,
+            text_glyphs,
+            font_key,
+            ColorF::new(0.8, 0.8, 0.8, 1.0),
+            Au::from_f32_px(FONT_SIZE_PX),
+            Au::from_px(0),
+        );
 
-                // Debug draw bounding boxes for each glyph.
-                // ================================================================================
-                if !DEBUG_GLYPHS { return; }
+        // Show a cursor at the end of the focused field.
+        if field == find_panel.focused_field {
+            let cursor_height = FONT_SIZE_PX * LINE_HEIGHT;
+            let row_top = bounds.origin.y + FIND_PANEL_ROW_HEIGHT * row as f32;
+            builder.push_rect(
+                LayoutRect::new(
+                    LayoutPoint::new(text_right, row_top + (FIND_PANEL_ROW_HEIGHT - cursor_height) / 2.0),
+                    LayoutSize::new(1.0, cursor_height),
+                ),
+                clip_region,
+                ColorF::new(1.0, 1.0, 1.0, 1.0),
+            );
+        }
+    }
+
+    // Show the search options at the right end of the first row, with the ones that are turned on
+    // highlighted. They're laid out right to left so that they stay against the edge.
+    let options = [
+        ("Word", find_panel.whole_words),
+        ("Regex", find_panel.regex),
+        ("Case", find_panel.case_sensitive),
+    ];
+    let baseline = row_baseline(0.0);
+    let mut option_right = window_size.width - TAB_PADDING / 2.0;
+    for &(label, enabled) in &options {
+        let label_width = layout_text(font, font_scale, label, point(0.0, 0.0)).1;
+        let option_left = option_right - label_width - TAB_PADDING;
+        let (glyphs, _) = layout_text(font, font_scale, label, point(option_left + TAB_PADDING / 2.0, baseline));
+
+        let text_color = if enabled {
+            builder.push_rect(
+                LayoutRect::new(
+                    LayoutPoint::new(option_left, bounds.origin.y + 4.0),
+                    LayoutSize::new(option_right - option_left, FIND_PANEL_ROW_HEIGHT - 8.0),
+                ),
+                clip_region,
+                ColorF::new(0.3, 0.5, 0.9, 1.0),
+            );
+            ColorF::new(1.0, 1.0, 1.0, 1.0)
+        } else {
+            ColorF::new(0.5, 0.5, 0.5, 1.0)
+        };
+        builder.push_text(
+            bounds,
+            clip_region,
+            glyphs,
+            font_key,
+            text_color,
+            Au::from_f32_px(FONT_SIZE_PX),
+            Au::from_px(0),
+        );
+
+        option_right = option_left - TAB_PADDING / 2.0;
+    }
+}
+
+/// Draws a prompt along the bottom of the window, showing its label followed by the text entered so
+/// far.
+fn push_prompt(
+    builder: &mut DisplayListBuilder,
+    font_key: FontKey,
+    font: &Font,
+    prompt: &Prompt,
+    window_size: LayoutSize,
+) {
+    let bounds = LayoutRect::new(
+        LayoutPoint::new(0.0, window_size.height - PROMPT_HEIGHT),
+        LayoutSize::new(window_size.width, PROMPT_HEIGHT),
+    );
+    let clip_region = webrender_traits::ClipRegion::simple(&bounds);
+
+    builder.push_rect(bounds, clip_region, ColorF::new(0.15, 0.15, 0.15, 1.0));
+
+    // TODO: See `push_editor()` for why we're scaling the font.
+    let font_scale = Scale::uniform(FONT_SIZE_PX / PIXEL_TO_POINT);
+    let v_metrics = font.v_metrics(font_scale);
+    let baseline = bounds.origin.y + PROMPT_HEIGHT / 2.0 + (v_metrics.ascent + v_metrics.descent) / 2.0;
+
+    let (label_glyphs, label_right) = layout_text(font, font_scale, prompt.label, point(TAB_PADDING, baseline));
+    let (text_glyphs, text_right) = layout_text(
+        font,
+        font_scale,
+        &*prompt.text,
+        point(label_right + TAB_PADDING / 2.0, baseline),
+    );
+
+    builder.push_text(
+        bounds,
+        clip_region,
+        label_glyphs,
+        font_key,
+        ColorF::new(0.5, 0.5, 0.5, 1.0),
+        Au::from_f32_px(FONT_SIZE_PX),
+        Au::from_px(0),
+    );
+    builder.push_text(
+        bounds,
+        clip_region,
+        text_glyphs,
+        font_key,
+        ColorF::new(0.8, 0.8, 0.8, 1.0),
+        Au::from_f32_px(FONT_SIZE_PX),
+        Au::from_px(0),
+    );
+
+    // Show a cursor at the end of the text.
+    let cursor_height = FONT_SIZE_PX * LINE_HEIGHT;
+    builder.push_rect(
+        LayoutRect::new(
+            LayoutPoint::new(text_right, bounds.origin.y + (PROMPT_HEIGHT - cursor_height) / 2.0),
+            LayoutSize::new(1.0, cursor_height),
+        ),
+        clip_region,
+        ColorF::new(1.0, 1.0, 1.0, 1.0),
+    );
+}
+
+/// Draws the contents of an editor with its top-left corner at `origin`, in a scroll layer with the
+/// given id. Cursors are drawn hollow unless the editor has keyboard focus, in which case they're
+/// only drawn if `cursor_visible` is set, so that they can blink.
+fn push_editor(
+    builder: &mut DisplayListBuilder,
+    font_key: FontKey,
+    font: &Font,
+    config: &Config,
+    editor: &mut EditorState,
+    origin: LayoutPoint,
+    scroll_root_id: ServoScrollRootId,
+    focused: bool,
+    cursor_visible: bool,
+) {
+    let view_width = editor.view_width_pixels as f32;
+    let view_height = editor.view_height_pixels as f32;
+
+    let bounds = LayoutRect::new(origin, LayoutSize::new(view_width, view_height));
+    let clip_region = {
+        let complex = webrender_traits::ComplexClipRegion::new(
+            bounds,
+            webrender_traits::BorderRadius::uniform(0.0),
+        );
+
+        builder.new_clip_region(&bounds, vec![complex], None)
+    };
+
+    // Sample text to demonstrate text layout and rendering.
+    let em_border = BorderSide {
+        width: 1.0,
+        color: ColorF::new(1.0, 0.0, 1.0, 1.0),
+        style: BorderStyle::Solid,
+    };
+    let glyph_border = BorderSide {
+        width: 1.0,
+        color: ColorF::new(1.0, 0.0, 0.0, 1.0),
+        style: BorderStyle::Solid,
+    };
+
+    // TODO: Investigate why this scaling is necessary. Rusttype says it takes font scale in pixels,
+    // but glyphs rendered with the system renderer don't match the sizes produced by rusttype
+    // unless we slightly tweak the rusttype scale. I used Atom displaying the Hack-Regular font at
+    // 14px to compare, so if this is actually wrong blame Atom.
+    //
+    // Issue tracker: https://github.com/excaliburHisSheath/text-edit/issues/4
+    let font_scale = Scale::uniform(FONT_SIZE_PX / PIXEL_TO_POINT);
+    let v_metrics = font.v_metrics(font_scale);
+    let line_height = FONT_SIZE_PX * LINE_HEIGHT;
+
+    // There's nothing to scroll until xi-core has told us how long the document is.
+    let scroll_to_line = if editor.height_in_lines > 0 {
+        editor.scroll_to_line.take()
+    } else {
+        None
+    };
+
+    if let Some(scroll_to_line) = scroll_to_line {
+        let line_top = scroll_to_line as f32 * line_height;
+        let line_bottom = scroll_to_line as f32 * line_height + line_height;
+
+        // Compare against where the view will end up, in case it's already scrolling.
+        let scroll_target = editor.scroll_target();
+        let view_top = scroll_target + line_height * 2.0;
+        let view_bottom = scroll_target + editor.view_height_pixels as f32 - line_height * 2.0;
+
+        // TODO: We could use a `clamp()` operation to represent this more clearly, I think?
+        if editor.center_scroll_to_line {
+            editor.center_scroll_to_line = false;
+
+            let scroll_target = line_top + line_height / 2.0 - editor.view_height_pixels as f32 / 2.0;
+            editor.animate_scroll_to(scroll_target, config);
+        } else if view_top > line_top {
+            // Scroll view upwards to match line top.
+            editor.animate_scroll_to(line_top - line_height * 2.0, config);
+        } else if view_bottom < line_bottom {
+            // Scroll view downwards to match line bottom.
+            let scroll_target = line_bottom - editor.view_height_pixels as f32 + line_height * 2.0;
+            editor.animate_scroll_to(scroll_target, config);
+        }
+    }
+
+    // The document is drawn into a scroll layer the size of the whole document, so that scrolling
+    // only requires webrender to move the layer rather than building a new display list. The
+    // layer's scroll position is set separately, see `scroll_layers()`.
+    let content_size = LayoutSize::new(view_width, (editor.height_in_lines as f32 * line_height).max(view_height));
+    builder.push_scroll_layer(clip_region, content_size, Some(scroll_root_id));
+
+    // Everything in the scroll layer is positioned and clipped relative to the whole document
+    // rather than to the view.
+    let text_bounds = LayoutRect::new(origin, content_size);
+    let clip_region = webrender_traits::ClipRegion::simple(&text_bounds);
+
+    // The gutter runs down the left side of the document, with the text to the right of it.
+    let gutter_width = gutter_width(font, editor);
+    builder.push_rect(
+        LayoutRect::new(origin, LayoutSize::new(gutter_width, content_size.height)),
+        clip_region,
+        ColorF::new(0.08, 0.08, 0.08, 1.0),
+    );
+
+    // A cursor at the end of a line doesn't have a character to cover, so it's as wide as a space.
+    let space_width = layout_text(font, font_scale, " ", point(0.0, 0.0)).1;
+
+    // Relative line numbers count from the line with the primary cursor, even while it's scrolled
+    // out of view.
+    let cursor_line = editor.cursor_position.0;
+
+    for (index, line) in editor.lines.iter().enumerate() {
+        let line_number = editor.first_line + index;
+        let baseline = origin.y + line_baseline(line_number);
+
+        // Draw the line number, right-aligned in the gutter. Lines with a cursor are highlighted.
+        // ========================================================================================
+        let label = if config.relative_line_numbers && line_number != cursor_line {
+            (cmp::max(line_number, cursor_line) - cmp::min(line_number, cursor_line)).to_string()
+        } else {
+            (line_number + 1).to_string()
+        };
+
+        // The label's width isn't known until it's laid out, so lay it out at the left edge and
+        // then move it into place.
+        let (mut label_glyphs, label_width) = layout_text(font, font_scale, &*label, point(0.0, baseline));
+        let label_left = origin.x + gutter_width - GUTTER_PADDING - label_width;
+        for glyph in &mut label_glyphs {
+            glyph.x += label_left;
+        }
+        let label_color = if line.cursors.is_empty() {
+            ColorF::new(0.4, 0.4, 0.4, 1.0)
+        } else {
+            ColorF::new(0.8, 0.8, 0.8, 1.0)
+        };
+        builder.push_text(
+            text_bounds,
+            clip_region,
+            label_glyphs,
+            font_key,
+            label_color,
+            Au::from_f32_px(FONT_SIZE_PX),
+            Au::from_px(0),
+        );
+
+        let origin = point(origin.x + gutter_width, baseline);
+
+        let line_middle = origin.y - v_metrics.ascent - v_metrics.descent + (v_metrics.ascent + v_metrics.descent) / 2.0;
+        let line_top = line_middle - line_height / 2.0;
+
+        let (positioned_glyphs, glyph_edges) = layout_line(font, font_scale, &*line.text, origin);
+        let line_end = *glyph_edges.last().unwrap();
+
+        // Debug draw bounding boxes for each glyph.
+        // ========================================================================================
+        if DEBUG_GLYPHS {
+            for glyph in &positioned_glyphs {
+                let pos = glyph.position();
+                let h_metrics = glyph.unpositioned().h_metrics();
 
                 // Draw border based on rusttype scaled glyph.
                 let rect = LayoutRect::new(
@@ -468,8 +1875,12 @@ fn build_display_lists(
                         webrender_traits::BorderRadius::uniform(0.0),
                     );
                 }
-            })
-            .map(|(_, glyph)| {
+            }
+        }
+
+        let glyphs: Vec<GlyphInstance> = positioned_glyphs
+            .iter()
+            .map(|glyph| {
                 GlyphInstance {
                     index: glyph.id().0,
                     x: glyph.position().x,
@@ -478,42 +1889,313 @@ fn build_display_lists(
             })
             .collect();
 
-        // Render a cursor at the end of the line if necessary.
-        for cursor_col in &line.cursors {
-            if *cursor_col == last_index {
-                let line_middle = origin.y - v_metrics.ascent - v_metrics.descent + (v_metrics.ascent + v_metrics.descent) / 2.0;
-                let line_top = line_middle - line_height / 2.0;
+        // Highlight lines containing a cursor, beneath everything else in the line.
+        // ========================================================================================
+        if !line.cursors.is_empty() {
+            builder.push_rect(
+                LayoutRect::new(
+                    LayoutPoint::new(origin.x, line_top),
+                    LayoutSize::new(bounds.origin.x + bounds.size.width - origin.x, line_height),
+                ),
+                clip_region,
+                config.current_line_color,
+            );
+        }
+
+        // Draw matches of the current search beneath the text and selections.
+        // ========================================================================================
+        for &(start, end) in &line.find_matches {
+            let left = glyph_edges.get(start).cloned().unwrap_or(line_end);
+            let right = glyph_edges.get(end).cloned().unwrap_or(line_end);
 
-                // Draw a cursor at this col.
+            if right > left {
                 builder.push_rect(
                     LayoutRect::new(
-                        LayoutPoint::new(line_end, line_top),
-                        LayoutSize::new(1.0, line_height),
+                        LayoutPoint::new(left, line_top),
+                        LayoutSize::new(right - left, line_height),
                     ),
                     clip_region,
-                    ColorF::new(1.0, 1.0, 1.0, 1.0),
+                    config.find_match_color,
                 );
             }
         }
 
-        builder.push_text(
-            text_bounds,
-            webrender_traits::ClipRegion::simple(&bounds),
-            glyphs,
-            font_key,
-            ColorF::new(0.8, 0.8, 0.8, 1.0),
-            Au::from_f32_px(FONT_SIZE_PX),
-            Au::from_px(0),
-        );
+        // Draw selections beneath the text.
+        // ========================================================================================
+        for &(start, end) in &line.selections {
+            let left = glyph_edges.get(start).cloned().unwrap_or(line_end);
+
+            // A selection that continues past the last character includes the newline, so it's
+            // extended to the edge of the view to show that it continues onto the next line.
+            let right = match glyph_edges.get(end) {
+                Some(&right) => right,
+                None => bounds.origin.x + bounds.size.width,
+            };
+
+            if right > left {
+                builder.push_rect(
+                    LayoutRect::new(
+                        LayoutPoint::new(left, line_top),
+                        LayoutSize::new(right - left, line_height),
+                    ),
+                    clip_region,
+                    config.selection_color,
+                );
+            }
+        }
+
+        // Draw cursors where appropriate.
+        // ========================================================================================
+        let cursors: &[usize] = if focused && !cursor_visible { &[] } else { &line.cursors };
+        let cursors_cover_text = config.cursor_style.covers_text(focused);
+        for &cursor_col in cursors {
+            if let Some(&cursor_x) = glyph_edges.get(cursor_col) {
+                let cell_width = match glyph_edges.get(cursor_col + 1) {
+                    Some(&right) if right > cursor_x => right - cursor_x,
+                    _ => space_width,
+                };
+                let cell = LayoutRect::new(
+                    LayoutPoint::new(cursor_x, line_top),
+                    LayoutSize::new(cell_width, line_height),
+                );
+                cursor::push_cursor(builder, clip_region, cell, &config.cursor_style, focused);
+            }
+        }
+
+        // Split the line into runs of glyphs that share a style, since each text item can only be
+        // drawn in a single color.
+        // ========================================================================================
+        let mut run_start = 0;
+        for run_end in 1..glyphs.len() + 1 {
+            let run_style = line.style_at(run_start);
+            if run_end < glyphs.len() && line.style_at(run_end) == run_style {
+                continue;
+            }
+
+            builder.push_text(
+                text_bounds,
+                clip_region,
+                glyphs[run_start..run_end].to_vec(),
+                font_key,
+                run_style.color,
+                Au::from_f32_px(FONT_SIZE_PX),
+                Au::from_px(0),
+            );
+
+            // TODO: We only have a regular font face, so bold text is faked by drawing the run a
+            // second time offset by a pixel, and italic text is drawn as regular text.
+            if run_style.bold {
+                let bold_glyphs = glyphs[run_start..run_end]
+                    .iter()
+                    .map(|glyph| {
+                        GlyphInstance {
+                            index: glyph.index,
+                            x: glyph.x + 1.0,
+                            y: glyph.y,
+                        }
+                    })
+                    .collect();
+                builder.push_text(
+                    text_bounds,
+                    clip_region,
+                    bold_glyphs,
+                    font_key,
+                    run_style.color,
+                    Au::from_f32_px(FONT_SIZE_PX),
+                    Au::from_px(0),
+                );
+            }
+
+            run_start = run_end;
+        }
+
+        // Draw the characters under block cursors again so that they show up against the cursor.
+        // ========================================================================================
+        if cursors_cover_text {
+            for &cursor_col in cursors {
+                if let Some(&glyph) = glyphs.get(cursor_col) {
+                    builder.push_text(
+                        text_bounds,
+                        clip_region,
+                        vec![glyph],
+                        font_key,
+                        config.cursor_style.text_color(),
+                        Au::from_f32_px(FONT_SIZE_PX),
+                        Au::from_px(0),
+                    );
+                }
+            }
+        }
     }
 
-    builder.pop_stacking_context();
+    builder.pop_scroll_layer();
+}
 
-    builder
+/// Returns the bounds of the whole window.
+fn window_bounds(window_width: u32, window_height: u32) -> LayoutRect {
+    LayoutRect::new(LayoutPoint::new(0.0, 0.0), LayoutSize::new(window_width as f32, window_height as f32))
+}
+
+/// Returns the part of the window that the panes are laid out in, i.e. everything above the status
+/// bar.
+fn pane_area(window_bounds: LayoutRect) -> LayoutRect {
+    let height = (window_bounds.size.height - STATUS_BAR_HEIGHT).max(0.0);
+    LayoutRect::new(window_bounds.origin, LayoutSize::new(window_bounds.size.width, height))
+}
+
+/// Converts a point in the window to a point relative to the top-left corner of the editor in the
+/// pane with the given bounds.
+fn editor_position(pane_bounds: LayoutRect, position: LayoutPoint) -> LayoutPoint {
+    LayoutPoint::new(
+        position.x - pane_bounds.origin.x,
+        position.y - pane_bounds.origin.y - TAB_BAR_HEIGHT,
+    )
+}
+
+/// Returns the y coordinate of the baseline of a line, relative to the top of the document.
+fn line_baseline(line: usize) -> f32 {
+    // TODO: There seems to be a 5 pixel gap at the top of the window on Windows. Is this something
+    // we're accidentally introducing, or is it created by webrender somehow?
+    (line + 1) as f32 * FONT_SIZE_PX * LINE_HEIGHT - 5.0
+}
+
+/// Returns the id of the scroll layer holding the active editor of the pane at `pane_index`.
+///
+/// Ids start at 1 so that they don't clash with the pipeline's root scroll layer.
+fn scroll_root_id(pane_index: usize) -> ServoScrollRootId {
+    ServoScrollRootId(pane_index + 1)
+}
+
+/// Scrolls the scroll layer of each pane to its active editor's scroll offset.
+///
+/// Scrolling a layer makes webrender render a new frame on its own, without needing a new display
+/// list.
+fn scroll_layers(api: &RenderApi, pipeline_id: PipelineId, panes: &Panes) {
+    for (index, pane) in panes.panes().into_iter().enumerate() {
+        let scroll_origin = LayoutPoint::new(0.0, pane.active_editor().scroll_offset_pixels);
+        api.scroll_layers_with_scroll_id(scroll_origin, pipeline_id, scroll_root_id(index));
+    }
+}
+
+/// Returns the width of the line number gutter along the left side of an editor, which is wide
+/// enough for the number of the document's last line.
+fn gutter_width(font: &Font, editor: &EditorState) -> f32 {
+    // TODO: See `push_editor()` for why we're scaling the font.
+    let font_scale = Scale::uniform(FONT_SIZE_PX / PIXEL_TO_POINT);
+    let widest_label = cmp::max(editor.height_in_lines, 1).to_string();
+    let label_width = layout_text(font, font_scale, &*widest_label, point(0.0, 0.0)).1;
+    label_width + GUTTER_PADDING * 2.0
+}
+
+/// Lays out a line of the document with its baseline starting at `origin`.
+///
+/// Returns the positioned glyphs along with the left edge of each glyph, so that cursors and
+/// selections can be placed once the line has been laid out. The right edge of the last glyph is
+/// added at the end so that there's a position for each column, including the one after the last
+/// character.
+fn layout_line<'a>(font: &Font<'a>, scale: Scale, text: &str, origin: Point<f32>) -> (Vec<PositionedGlyph<'a>>, Vec<f32>) {
+    let glyphs: Vec<PositionedGlyph> = font.layout(text, scale, origin).collect();
+
+    let mut edges: Vec<f32> = glyphs.iter().map(|glyph| glyph.position().x).collect();
+    let line_end = glyphs
+        .last()
+        .map(|glyph| glyph.position().x + glyph.unpositioned().h_metrics().advance_width)
+        .unwrap_or(origin.x);
+    edges.push(line_end);
+
+    (glyphs, edges)
+}
+
+/// Finds the `(line, column)` in the document under a point, given relative to the top-left corner
+/// of the editor's view.
+///
+/// Lines are laid out the same way as in `push_editor()`, so the position matches what's on
+/// screen. Points past the end of a line map to the end of the line, and points below the end of
+/// the document map to the last line.
+fn hit_test(font: &Font, editor: &EditorState, position: LayoutPoint) -> (usize, usize) {
+    // TODO: See `push_editor()` for why we're scaling the font.
+    let font_scale = Scale::uniform(FONT_SIZE_PX / PIXEL_TO_POINT);
+    let v_metrics = font.v_metrics(font_scale);
+    let line_height = FONT_SIZE_PX * LINE_HEIGHT;
+
+    let first_line_top = line_baseline(0)
+        - (v_metrics.ascent + v_metrics.descent) / 2.0
+        - line_height / 2.0
+        - editor.scroll_offset_pixels;
+    let line = ((position.y - first_line_top) / line_height).max(0.0) as usize;
+    let line = if editor.height_in_lines > 0 {
+        cmp::min(line, editor.height_in_lines - 1)
+    } else {
+        line
+    };
+
+    // We can only find the column if we have the line's text.
+    let line_contents = match line.checked_sub(editor.first_line).and_then(|index| editor.lines.get(index)) {
+        Some(line_contents) => line_contents,
+        None => return (line, 0),
+    };
+
+    // Pick whichever column boundary is closest to the point.
+    let text_left = gutter_width(font, editor);
+    let (_, glyph_edges) = layout_line(font, font_scale, &*line_contents.text, point(text_left, 0.0));
+    let col = glyph_edges
+        .windows(2)
+        .position(|edges| position.x < (edges[0] + edges[1]) / 2.0)
+        .unwrap_or(glyph_edges.len() - 1);
+
+    // Don't place the cursor after the line's newline.
+    let line_len = line_contents.text.chars().filter(|&c| c != '\n' && c != '\r').count();
+    (line, cmp::min(col, line_len))
+}
+
+/// Converts a color packed as `0xAARRGGBB`, which is how xi-core sends colors, to a `ColorF`.
+fn color_from_argb(argb: u32) -> ColorF {
+    ColorF::new(
+        ((argb >> 16) & 0xff) as f32 / 255.0,
+        ((argb >> 8) & 0xff) as f32 / 255.0,
+        (argb & 0xff) as f32 / 255.0,
+        ((argb >> 24) & 0xff) as f32 / 255.0,
+    )
+}
+
+/// Lays out a single line of text starting at `origin`.
+///
+/// Returns the glyphs to be rendered and the x coordinate of the right edge of the text.
+fn layout_text(font: &Font, scale: Scale, text: &str, origin: Point<f32>) -> (Vec<GlyphInstance>, f32) {
+    let mut right = origin.x;
+    let glyphs = font
+        .layout(text, scale, origin)
+        .inspect(|glyph| right = glyph.position().x + glyph.unpositioned().h_metrics().advance_width)
+        .map(|glyph| {
+            GlyphInstance {
+                index: glyph.id().0,
+                x: glyph.position().x,
+                y: glyph.position().y,
+            }
+        })
+        .collect();
+
+    (glyphs, right)
 }
 
 #[derive(Debug)]
 struct EditorState {
+    /// The name of the xi-core tab displayed by this editor.
+    ///
+    /// This is assigned by xi-core in response to `new_tab`, and is used to identify the tab in
+    /// all edit commands sent to xi-core and all updates received from it.
+    tab: String,
+
+    /// The path of the file open in the editor, or `None` if the document is untitled.
+    file_path: Option<String>,
+
+    /// The directory containing `file_path` as shown in the window title, see
+    /// `display_directory()`.
+    ///
+    /// This is worked out whenever the path changes rather than each time the title is updated,
+    /// since it depends on the current and home directories.
+    parent_directory: Option<String>,
+
     /// The total number of lines in the document.
     height_in_lines: usize,
 
@@ -545,6 +2227,358 @@ struct EditorState {
     ///
     /// TODO: does this setup (scrolling top-to-botton) still make sense for non-western layouts?
     scroll_offset_pixels: f32,
+
+    /// The `(line, column)` of the primary cursor, as of the last `scrollto` from xi-core.
+    ///
+    /// A new document starts out with the cursor at its beginning.
+    cursor_position: (usize, usize),
+
+    /// The name of the line ending used by the document, i.e. "LF" or "CRLF".
+    ///
+    /// This is the line ending used by the file when it was opened, see `detect_line_ending()`.
+    line_ending: &'static str,
+
+    /// A line that needs to be scrolled into view the next time the editor is drawn.
+    ///
+    /// This is set when xi-core sends a `scrollto` for the tab, which may happen while the editor
+    /// isn't visible.
+    scroll_to_line: Option<usize>,
+
+    /// Whether `scroll_to_line` should be scrolled to the middle of the view rather than just into
+    /// view, e.g. after going to a line.
+    center_scroll_to_line: bool,
+
+    /// An animation smoothly scrolling the view, see `animate_scroll_to()`.
+    scroll_animation: Option<Animation>,
+
+    /// Momentum left over from flinging a trackpad, which keeps the view scrolling after the
+    /// trackpad has been let go.
+    scroll_momentum: Option<Momentum>,
+
+    /// The range of lines that xi-core was last told are visible, see `sync_visible_range()`.
+    scrolled_range: (usize, usize),
+
+    /// The number of edits that have changed the document since it was opened.
+    ///
+    /// Undoing and redoing count as edits too, so undoing back to the saved document still counts
+    /// as having unsaved changes. That way closing the editor never throws away work without asking.
+    edit_count: usize,
+
+    /// The number of edits that an undo could act on, see `record_edit()`.
+    ///
+    /// xi-core doesn't say how much history it has, and it may group several edits together, e.g.
+    /// a run of typing. So this can only be an upper bound, which errs on the side of treating an
+    /// undo as a change.
+    undo_count: usize,
+
+    /// The number of undone edits that a redo could act on.
+    redo_count: usize,
+
+    /// The value of `edit_count` when the document was last saved.
+    ///
+    /// The document has unsaved changes whenever the two counts differ.
+    saved_edit_count: usize,
+
+    /// A save that xi-core hasn't finished yet.
+    pending_save: Option<PendingSave>,
+
+    /// Whether the file has been saved from another editor since this one loaded it.
+    ///
+    /// Each editor has its own copy of the document in xi-core, so saving this copy would overwrite
+    /// the other editor's changes.
+    is_stale: bool,
+}
+
+impl EditorState {
+    /// Creates an editor for the xi-core tab named `tab`.
+    ///
+    /// Until the editor is laid out it assumes that the first `visible_lines` lines of the document
+    /// are visible.
+    fn new(tab: String, file_path: Option<String>, visible_lines: usize) -> EditorState {
+        let parent_directory = file_path.as_ref().and_then(|path| display_directory(&*path));
+        EditorState {
+            tab: tab,
+            file_path: file_path,
+            parent_directory: parent_directory,
+            height_in_lines: 0,
+            first_line: 0,
+            lines: vec![
+                LineContents {
+                    text: "".into(),
+                    cursors: vec![0],
+                    selections: Vec::new(),
+                    find_matches: Vec::new(),
+                    styles: Vec::new(),
+                },
+            ],
+            view_width_pixels: 0,
+            view_height_pixels: 0,
+            scroll_offset_pixels: 0.0,
+            cursor_position: (0, 0),
+            line_ending: "LF",
+            scroll_to_line: None,
+            center_scroll_to_line: false,
+            scroll_animation: None,
+            scroll_momentum: None,
+            scrolled_range: (0, visible_lines),
+            edit_count: 0,
+            undo_count: 0,
+            redo_count: 0,
+            saved_edit_count: 0,
+            pending_save: None,
+            is_stale: false,
+        }
+    }
+
+    /// Returns the name to display for the editor, i.e. the file name of the open document.
+    fn title(&self) -> &str {
+        self.file_path
+            .as_ref()
+            .and_then(|path| Path::new(path).file_name())
+            .and_then(|file_name| file_name.to_str())
+            .unwrap_or("untitled")
+    }
+
+    /// Changes the path of the open document, e.g. after saving it under a new name.
+    fn set_file_path(&mut self, file_path: String) {
+        self.parent_directory = display_directory(&*file_path);
+        self.file_path = Some(file_path);
+    }
+
+    /// Returns the number of whole lines that fit in the editor's view.
+    fn visible_line_count(&self) -> usize {
+        (self.view_height_pixels as f32 / (FONT_SIZE_PX * LINE_HEIGHT)) as usize
+    }
+
+    /// Returns the range of lines in the view as `(first, last)`, where `last` is exclusive.
+    ///
+    /// This includes any lines that are only partially visible.
+    fn visible_range(&self) -> (usize, usize) {
+        let first_line = (self.scroll_offset_pixels / (FONT_SIZE_PX * LINE_HEIGHT)) as usize;
+        (first_line, first_line + self.visible_line_count() + 2)
+    }
+
+    /// Keeps the scroll offset from going past either end of the document.
+    fn clamp_scroll_offset(&mut self) {
+        self.scroll_offset_pixels = self.clamped_scroll_offset(self.scroll_offset_pixels);
+    }
+
+    /// Limits a scroll offset to the document. The view can scroll down until the last line of the
+    /// document is at the bottom of the view.
+    fn clamped_scroll_offset(&self, scroll_offset: f32) -> f32 {
+        let document_height = self.height_in_lines as f32 * FONT_SIZE_PX * LINE_HEIGHT;
+        let max_scroll_offset = (document_height - self.view_height_pixels as f32).max(0.0);
+        scroll_offset.max(0.0).min(max_scroll_offset)
+    }
+
+    /// Returns the scroll offset the view is animating towards, or the current scroll offset if
+    /// it isn't animating.
+    fn scroll_target(&self) -> f32 {
+        self.scroll_animation
+            .map(|animation| animation.target())
+            .unwrap_or(self.scroll_offset_pixels)
+    }
+
+    /// Smoothly scrolls the view to `scroll_offset`, or jumps straight there if smooth scrolling
+    /// is disabled.
+    fn animate_scroll_to(&mut self, scroll_offset: f32, config: &Config) {
+        let scroll_offset = self.clamped_scroll_offset(scroll_offset);
+        self.stop_scrolling();
+
+        if config.scroll_duration == Duration::from_millis(0) {
+            self.scroll_offset_pixels = scroll_offset;
+        } else {
+            self.scroll_animation = Some(Animation::new(
+                self.scroll_offset_pixels,
+                scroll_offset,
+                config.scroll_duration,
+                config.scroll_easing,
+                Instant::now(),
+            ));
+        }
+    }
+
+    /// Keeps the view scrolling at `velocity` pixels per second, slowing down over time.
+    fn start_momentum(&mut self, velocity: f32, now: Instant) {
+        self.stop_scrolling();
+        self.scroll_momentum = Some(Momentum::new(velocity, now));
+    }
+
+    /// Stops any animation or momentum, leaving the view where it currently is.
+    fn stop_scrolling(&mut self) {
+        self.scroll_animation = None;
+        self.scroll_momentum = None;
+    }
+
+    /// Returns `true` if the view is animating or has momentum.
+    fn is_scrolling(&self) -> bool {
+        self.scroll_animation.is_some() || self.scroll_momentum.is_some()
+    }
+
+    /// Advances any scrolling animation or momentum to `now`.
+    ///
+    /// Returns `true` if the view moved.
+    fn update_scroll(&mut self, now: Instant) -> bool {
+        if let Some(animation) = self.scroll_animation.take() {
+            self.scroll_offset_pixels = animation.value_at(now);
+            if !animation.is_finished(now) {
+                self.scroll_animation = Some(animation);
+            }
+        } else if let Some(mut momentum) = self.scroll_momentum.take() {
+            let scroll_offset = self.scroll_offset_pixels + momentum.step(now);
+            self.scroll_offset_pixels = self.clamped_scroll_offset(scroll_offset);
+
+            // Stop once the momentum has died down or the view has hit either end of the document.
+            if !momentum.is_stopped() && self.scroll_offset_pixels == scroll_offset {
+                self.scroll_momentum = Some(momentum);
+            }
+        } else {
+            return false;
+        }
+
+        true
+    }
+
+    /// Returns the line number and contents of the line containing the primary cursor, along with
+    /// the cursor's column, if xi-core has sent that line.
+    fn primary_cursor(&self) -> Option<(usize, &LineContents, usize)> {
+        let (line, col) = self.cursor_position;
+        line.checked_sub(self.first_line)
+            .and_then(|index| self.lines.get(index))
+            .map(|contents| (line, contents, col))
+    }
+
+    /// Returns `true` if xi-core has sent every line of the document, rather than only the ones
+    /// in view.
+    fn has_all_lines(&self) -> bool {
+        self.first_line == 0 && self.lines.len() >= self.height_in_lines
+    }
+
+    /// Returns the number of selections that are at least partly in the lines xi-core has sent.
+    ///
+    /// xi-core sends a selection that spans several lines as a separate range on each line, so a
+    /// range at the start of a line continues the selection from the previous line if that one ran
+    /// to the end of its line.
+    fn visible_selection_count(&self) -> usize {
+        let mut count = 0;
+        let mut continues_onto_next_line = false;
+        for line in &self.lines {
+            let line_len = line.text.chars().count();
+            for &(start, end) in &line.selections {
+                if !(start == 0 && continues_onto_next_line) && end > start {
+                    count += 1;
+                }
+            }
+
+            continues_onto_next_line = line.selections.iter().any(|&(_, end)| end >= line_len);
+        }
+
+        count
+    }
+
+    /// Keeps track of whether an edit command sent to xi-core changes the document.
+    ///
+    /// Undoing and redoing only count as changes while there's history for them to act on, so that
+    /// e.g. undoing in a document that was just opened doesn't leave it with unsaved changes.
+    fn record_edit(&mut self, command: EditCommand) {
+        match command {
+            EditCommand::Undo => {
+                if self.undo_count > 0 {
+                    self.undo_count -= 1;
+                    self.redo_count += 1;
+                    self.edit_count += 1;
+                }
+            }
+            EditCommand::Redo => {
+                if self.redo_count > 0 {
+                    self.redo_count -= 1;
+                    self.undo_count += 1;
+                    self.edit_count += 1;
+                }
+            }
+            command if command.modifies_buffer() => self.record_change(),
+            _ => {}
+        }
+    }
+
+    /// Keeps track of a new change to the document, which can then be undone but leaves nothing to
+    /// redo.
+    fn record_change(&mut self) {
+        self.edit_count += 1;
+        self.undo_count += 1;
+        self.redo_count = 0;
+    }
+
+    /// Returns the number of changes made since the document was last saved.
+    fn unsaved_edit_count(&self) -> usize {
+        self.edit_count - self.saved_edit_count
+    }
+
+    /// Returns `true` if the document has changed since it was last saved.
+    fn is_dirty(&self) -> bool {
+        self.edit_count != self.saved_edit_count
+    }
+
+    /// Checks whether xi-core has responded to a pending save.
+    ///
+    /// Returns `None` if there's no save or it hasn't finished yet, otherwise whether it succeeded
+    /// along with a message for the user if it didn't.
+    fn poll_save(&mut self) -> Option<std::result::Result<(), String>> {
+        let response = match self.pending_save {
+            Some(ref pending_save) => pending_save.response.try_recv(),
+            None => return None,
+        };
+
+        match response {
+            Ok(Ok(_)) => {
+                // Only the edits made before the save was sent are included in the saved file, so
+                // anything typed while waiting on xi-core is still unsaved.
+                let pending_save = self.pending_save.take().unwrap();
+                self.saved_edit_count = pending_save.edit_count;
+                self.is_stale = false;
+                self.set_file_path(pending_save.file_path);
+                Some(Ok(()))
+            }
+
+            Ok(Err(error)) => {
+                let pending_save = self.pending_save.take().unwrap();
+                Some(Err(format!("Failed to save {}: {}", pending_save.file_path, error)))
+            }
+
+            Err(TryRecvError::Empty) => None,
+
+            // xi-core has gone away, so the save is never going to finish.
+            Err(TryRecvError::Disconnected) => {
+                let pending_save = self.pending_save.take().unwrap();
+                Some(Err(format!("Failed to save {}: lost connection to xi-core", pending_save.file_path)))
+            }
+        }
+    }
+}
+
+/// A request to save an editor's document that's waiting on a response from xi-core.
+#[derive(Debug)]
+struct PendingSave {
+    response: Receiver<Response>,
+
+    /// The editor's `edit_count` when the save was requested.
+    edit_count: usize,
+
+    /// The path the document is being saved to.
+    file_path: String,
+}
+
+/// A request for the selected text in a tab that's waiting on a response from xi-core, so that the
+/// text can be put on a clipboard.
+#[derive(Debug)]
+struct PendingCopy {
+    response: Receiver<Response>,
+
+    /// The clipboard the text goes on.
+    selection: Selection,
+
+    /// The tab the text was cut from, or `None` if it was only copied.
+    cut_from: Option<String>,
 }
 
 #[derive(Debug)]
@@ -552,6 +2586,77 @@ struct LineContents {
     text: String,
     cursors: Vec<usize>,
     selections: Vec<(usize, usize)>,
+
+    /// The `(start, end)` columns of any matches of the current search.
+    find_matches: Vec<(usize, usize)>,
+
+    /// Style spans for the line, e.g. from syntax highlighting.
+    ///
+    /// Spans may overlap, in which case later spans take precedence.
+    styles: Vec<StyleSpan>,
+}
+
+impl LineContents {
+    /// Returns the number of whitespace characters at the start of the line.
+    fn indent(&self) -> usize {
+        self.text.chars().take_while(|&c| c == ' ' || c == '\t').count()
+    }
+
+    /// Returns the style of the text at the given column.
+    fn style_at(&self, col: usize) -> TextStyle {
+        self.styles
+            .iter()
+            .rev()
+            .find(|span| span.start <= col && col < span.end)
+            .map(|span| span.style)
+            .unwrap_or(TextStyle {
+                color: ColorF::new(0.8, 0.8, 0.8, 1.0),
+                bold: false,
+                italic: false,
+            })
+    }
+}
+
+impl From<xi::Line> for LineContents {
+    fn from(line: xi::Line) -> LineContents {
+        let styles = line.styles
+            .iter()
+            .map(|style| {
+                StyleSpan {
+                    start: style.start,
+                    end: style.end,
+                    style: TextStyle {
+                        color: color_from_argb(style.argb),
+                        bold: style.is_bold(),
+                        italic: style.is_italic(),
+                    },
+                }
+            })
+            .collect();
+
+        LineContents {
+            text: line.text,
+            cursors: line.cursors,
+            selections: line.selections,
+            find_matches: line.find_matches,
+            styles: styles,
+        }
+    }
+}
+
+/// A range of columns in a line that's drawn with a particular style.
+#[derive(Debug, Clone, Copy)]
+struct StyleSpan {
+    start: usize,
+    end: usize,
+    style: TextStyle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TextStyle {
+    color: ColorF,
+    bold: bool,
+    italic: bool,
 }
 
 /// Helper struct for updating the window when a frame is done processing.
@@ -585,3 +2690,45 @@ impl webrender_traits::RenderNotifier for Notifier {
 
     fn pipeline_size_changed(&mut self, _: PipelineId, _: Option<LayoutSize>) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_without_history_isnt_a_change() {
+        let mut editor = EditorState::new("tab".into(), None, 0);
+        editor.record_edit(EditCommand::Undo);
+        editor.record_edit(EditCommand::Redo);
+        assert!(!editor.is_dirty());
+    }
+
+    #[test]
+    fn undo_and_redo_with_history_are_changes() {
+        let mut editor = EditorState::new("tab".into(), None, 0);
+        editor.record_edit(EditCommand::Insert("a"));
+        editor.saved_edit_count = editor.edit_count;
+
+        editor.record_edit(EditCommand::Undo);
+        assert_eq!(editor.unsaved_edit_count(), 1);
+
+        // Only one edit was made, so there's nothing more to undo.
+        editor.record_edit(EditCommand::Undo);
+        assert_eq!(editor.unsaved_edit_count(), 1);
+
+        editor.record_edit(EditCommand::Redo);
+        assert_eq!(editor.unsaved_edit_count(), 2);
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut editor = EditorState::new("tab".into(), None, 0);
+        editor.record_edit(EditCommand::Insert("a"));
+        editor.record_edit(EditCommand::Undo);
+        editor.record_edit(EditCommand::Insert("b"));
+
+        let edit_count = editor.edit_count;
+        editor.record_edit(EditCommand::Redo);
+        assert_eq!(editor.edit_count, edit_count);
+    }
+}
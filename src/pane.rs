@@ -0,0 +1,459 @@
+//! Splitting the window into multiple panes.
+//!
+//! The window is divided by a tree of splits, where each leaf is a [`Pane`][Pane] with its own set
+//! of tabs. Every tab is a separate xi-core tab with its own scroll state, so two panes showing the
+//! same file can be scrolled independently. They also have separate copies of the document, so
+//! once one copy is saved the others are marked stale and can't be saved over it.
+//!
+//! Panes are identified by their index in a depth-first traversal of the tree, i.e. the order
+//! they appear in reading left-to-right and top-to-bottom.
+//!
+//! [Pane]: struct.Pane.html
+
+use EditorState;
+use webrender_traits::{LayoutPoint, LayoutRect, LayoutSize};
+
+/// The space left between adjacent panes, in pixels.
+const DIVIDER_WIDTH: f32 = 2.0;
+
+/// The direction in which a pane is split.
+///
+/// This follows Vim's naming: A horizontal split stacks the panes on top of each other, and a
+/// vertical split places them side by side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitDirection {
+    Horizontal,
+    Vertical,
+}
+
+/// A region of the window displaying one or more tabs.
+#[derive(Debug)]
+pub struct Pane {
+    /// The editors for the tabs in the pane, in the order they're displayed in the tab bar.
+    ///
+    /// This is never empty.
+    pub editors: Vec<EditorState>,
+
+    /// The index of the editor currently displayed in the pane.
+    pub active_editor: usize,
+}
+
+impl Pane {
+    pub fn new(editor: EditorState) -> Pane {
+        Pane {
+            editors: vec![editor],
+            active_editor: 0,
+        }
+    }
+
+    pub fn active_editor(&self) -> &EditorState {
+        &self.editors[self.active_editor]
+    }
+
+    pub fn active_editor_mut(&mut self) -> &mut EditorState {
+        &mut self.editors[self.active_editor]
+    }
+
+    /// Adds a new tab to the pane and makes it the active tab.
+    pub fn add_editor(&mut self, editor: EditorState) {
+        self.editors.push(editor);
+        self.active_editor = self.editors.len() - 1;
+    }
+
+    /// Removes the active tab from the pane, returning its editor.
+    ///
+    /// This may leave the pane without any tabs, in which case the pane should be closed.
+    pub fn close_active_editor(&mut self) -> EditorState {
+        let editor = self.editors.remove(self.active_editor);
+        if self.active_editor > 0 && self.active_editor == self.editors.len() {
+            self.active_editor -= 1;
+        }
+
+        editor
+    }
+
+    /// Switches to the next tab, wrapping around at the end.
+    pub fn next_editor(&mut self) {
+        self.active_editor = (self.active_editor + 1) % self.editors.len();
+    }
+
+    /// Switches to the previous tab, wrapping around at the beginning.
+    pub fn previous_editor(&mut self) {
+        self.active_editor = (self.active_editor + self.editors.len() - 1) % self.editors.len();
+    }
+}
+
+/// All of the panes in the window, along with which one has focus.
+#[derive(Debug)]
+pub struct Panes {
+    /// The root of the split tree.
+    ///
+    /// This is only `None` temporarily while the tree is being rebuilt.
+    root: Option<Layout>,
+
+    /// The index of the pane that receives input.
+    focused: usize,
+}
+
+impl Panes {
+    pub fn new(pane: Pane) -> Panes {
+        Panes {
+            root: Some(Layout::Pane(pane)),
+            focused: 0,
+        }
+    }
+
+    /// Returns the number of panes.
+    pub fn len(&self) -> usize {
+        self.root().len()
+    }
+
+    /// Returns the index of the focused pane.
+    pub fn focused_index(&self) -> usize {
+        self.focused
+    }
+
+    /// Returns the pane at `index`, if there is one.
+    pub fn pane(&self, index: usize) -> Option<&Pane> {
+        self.panes().into_iter().nth(index)
+    }
+
+    /// Returns the pane at `index`, if there is one.
+    pub fn pane_mut(&mut self, index: usize) -> Option<&mut Pane> {
+        self.panes_mut().into_iter().nth(index)
+    }
+
+    /// Gives focus to the pane at `index`.
+    pub fn focus(&mut self, index: usize) {
+        assert!(index < self.len(), "Pane index out of bounds");
+        self.focused = index;
+    }
+
+    pub fn focused(&self) -> &Pane {
+        self.panes().swap_remove(self.focused)
+    }
+
+    pub fn focused_mut(&mut self) -> &mut Pane {
+        let focused = self.focused;
+        self.panes_mut().swap_remove(focused)
+    }
+
+    /// Moves focus to the next pane, wrapping around at the end.
+    pub fn focus_next(&mut self) {
+        self.focused = (self.focused + 1) % self.len();
+    }
+
+    /// Moves focus to the previous pane, wrapping around at the beginning.
+    pub fn focus_previous(&mut self) {
+        self.focused = (self.focused + self.len() - 1) % self.len();
+    }
+
+    /// Splits the focused pane in two, placing `pane` after it and giving it focus.
+    pub fn split_focused(&mut self, direction: SplitDirection, pane: Pane) {
+        let root = self.root.take().unwrap();
+        self.root = Some(root.split(self.focused, direction, pane));
+        self.focused += 1;
+    }
+
+    /// Removes the focused pane, giving its space to its sibling.
+    ///
+    /// Returns `None` without removing anything if the focused pane is the only one left.
+    pub fn close_focused(&mut self) -> Option<Pane> {
+        if self.len() == 1 {
+            return None;
+        }
+
+        let root = self.root.take().unwrap();
+        let (root, removed) = root.remove(self.focused);
+        self.root = root;
+
+        if self.focused == self.len() {
+            self.focused -= 1;
+        }
+
+        Some(removed)
+    }
+
+    /// Returns all of the panes in order.
+    pub fn panes(&self) -> Vec<&Pane> {
+        let mut panes = Vec::new();
+        self.root().collect_panes(&mut panes);
+        panes
+    }
+
+    /// Returns all of the panes in order.
+    pub fn panes_mut(&mut self) -> Vec<&mut Pane> {
+        let mut panes = Vec::new();
+        self.root.as_mut().unwrap().collect_panes_mut(&mut panes);
+        panes
+    }
+
+    /// Finds the editor displaying the xi-core tab with the given name.
+    pub fn find_editor_mut(&mut self, tab: &str) -> Option<&mut EditorState> {
+        self.panes_mut()
+            .into_iter()
+            .flat_map(|pane| pane.editors.iter_mut())
+            .find(|editor| editor.tab == tab)
+    }
+
+    /// Marks every editor with `file_path` open as stale, apart from the one displaying `tab`, after
+    /// `tab` has been saved.
+    ///
+    /// Editors don't share xi-core tabs, so any other editor for the file has its own copy of the
+    /// document, which no longer matches what's on disk.
+    pub fn mark_stale_copies(&mut self, tab: &str, file_path: &str) {
+        for pane in self.panes_mut() {
+            for editor in &mut pane.editors {
+                if editor.tab != tab && editor.file_path.as_ref().map(|path| &**path) == Some(file_path) {
+                    editor.is_stale = true;
+                }
+            }
+        }
+    }
+
+    /// Divides `bounds` between the panes, returning the bounds of each pane in order.
+    pub fn layout(&self, bounds: LayoutRect) -> Vec<LayoutRect> {
+        let mut rects = Vec::new();
+        self.root().layout(bounds, &mut rects);
+        rects
+    }
+
+    fn root(&self) -> &Layout {
+        self.root.as_ref().unwrap()
+    }
+}
+
+/// A node in the split tree.
+#[derive(Debug)]
+enum Layout {
+    Pane(Pane),
+    Split {
+        direction: SplitDirection,
+        first: Box<Layout>,
+        second: Box<Layout>,
+    },
+}
+
+impl Layout {
+    /// Returns the number of panes in this part of the tree.
+    fn len(&self) -> usize {
+        match *self {
+            Layout::Pane(_) => 1,
+            Layout::Split { ref first, ref second, .. } => first.len() + second.len(),
+        }
+    }
+
+    /// Replaces the pane at `index` with a split containing that pane followed by `pane`.
+    fn split(self, index: usize, direction: SplitDirection, pane: Pane) -> Layout {
+        match self {
+            Layout::Pane(existing) => {
+                Layout::Split {
+                    direction: direction,
+                    first: Box::new(Layout::Pane(existing)),
+                    second: Box::new(Layout::Pane(pane)),
+                }
+            }
+
+            Layout::Split { direction: split_direction, first, second } => {
+                let first_len = first.len();
+                if index < first_len {
+                    Layout::Split {
+                        direction: split_direction,
+                        first: Box::new((*first).split(index, direction, pane)),
+                        second: second,
+                    }
+                } else {
+                    Layout::Split {
+                        direction: split_direction,
+                        first: first,
+                        second: Box::new((*second).split(index - first_len, direction, pane)),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes the pane at `index`, returning what's left of the tree and the removed pane.
+    ///
+    /// When one side of a split is removed the split is replaced by the other side.
+    fn remove(self, index: usize) -> (Option<Layout>, Pane) {
+        match self {
+            Layout::Pane(pane) => (None, pane),
+
+            Layout::Split { direction, first, second } => {
+                let first_len = first.len();
+                if index < first_len {
+                    let (first, removed) = (*first).remove(index);
+                    let remaining = match first {
+                        Some(first) => {
+                            Layout::Split {
+                                direction: direction,
+                                first: Box::new(first),
+                                second: second,
+                            }
+                        }
+                        None => *second,
+                    };
+                    (Some(remaining), removed)
+                } else {
+                    let (second, removed) = (*second).remove(index - first_len);
+                    let remaining = match second {
+                        Some(second) => {
+                            Layout::Split {
+                                direction: direction,
+                                first: first,
+                                second: Box::new(second),
+                            }
+                        }
+                        None => *first,
+                    };
+                    (Some(remaining), removed)
+                }
+            }
+        }
+    }
+
+    fn collect_panes<'a>(&'a self, panes: &mut Vec<&'a Pane>) {
+        match *self {
+            Layout::Pane(ref pane) => panes.push(pane),
+            Layout::Split { ref first, ref second, .. } => {
+                first.collect_panes(panes);
+                second.collect_panes(panes);
+            }
+        }
+    }
+
+    fn collect_panes_mut<'a>(&'a mut self, panes: &mut Vec<&'a mut Pane>) {
+        match *self {
+            Layout::Pane(ref mut pane) => panes.push(pane),
+            Layout::Split { ref mut first, ref mut second, .. } => {
+                first.collect_panes_mut(panes);
+                second.collect_panes_mut(panes);
+            }
+        }
+    }
+
+    /// Splits `bounds` evenly at each split, leaving a divider between the two sides.
+    fn layout(&self, bounds: LayoutRect, rects: &mut Vec<LayoutRect>) {
+        match *self {
+            Layout::Pane(_) => rects.push(bounds),
+
+            Layout::Split { direction, ref first, ref second } => {
+                let (first_bounds, second_bounds) = match direction {
+                    SplitDirection::Horizontal => {
+                        let height = ((bounds.size.height - DIVIDER_WIDTH) / 2.0).max(0.0);
+                        (
+                            LayoutRect::new(bounds.origin, LayoutSize::new(bounds.size.width, height)),
+                            LayoutRect::new(
+                                LayoutPoint::new(bounds.origin.x, bounds.origin.y + height + DIVIDER_WIDTH),
+                                LayoutSize::new(bounds.size.width, height),
+                            ),
+                        )
+                    }
+
+                    SplitDirection::Vertical => {
+                        let width = ((bounds.size.width - DIVIDER_WIDTH) / 2.0).max(0.0);
+                        (
+                            LayoutRect::new(bounds.origin, LayoutSize::new(width, bounds.size.height)),
+                            LayoutRect::new(
+                                LayoutPoint::new(bounds.origin.x + width + DIVIDER_WIDTH, bounds.origin.y),
+                                LayoutSize::new(width, bounds.size.height),
+                            ),
+                        )
+                    }
+                };
+
+                first.layout(first_bounds, rects);
+                second.layout(second_bounds, rects);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pane(tab: &str) -> Pane {
+        Pane::new(EditorState::new(tab.into(), None, 0))
+    }
+
+    /// Returns the tab shown in each pane, in order.
+    fn tabs(panes: &Panes) -> Vec<String> {
+        panes.panes().iter().map(|pane| pane.active_editor().tab.clone()).collect()
+    }
+
+    #[test]
+    fn split_places_new_pane_after_focused() {
+        let mut panes = Panes::new(pane("a"));
+        panes.split_focused(SplitDirection::Vertical, pane("b"));
+        assert_eq!(tabs(&panes), vec!["a", "b"]);
+        assert_eq!(panes.focused_index(), 1);
+
+        panes.focus(0);
+        panes.split_focused(SplitDirection::Horizontal, pane("c"));
+        assert_eq!(tabs(&panes), vec!["a", "c", "b"]);
+        assert_eq!(panes.focused_index(), 1);
+    }
+
+    #[test]
+    fn close_focused_gives_space_to_sibling() {
+        let mut panes = Panes::new(pane("a"));
+        panes.split_focused(SplitDirection::Vertical, pane("b"));
+        panes.focus(0);
+        panes.split_focused(SplitDirection::Horizontal, pane("c"));
+
+        let removed = panes.close_focused().unwrap();
+        assert_eq!(removed.active_editor().tab, "c");
+        assert_eq!(tabs(&panes), vec!["a", "b"]);
+        assert_eq!(panes.focused_index(), 1);
+
+        // Closing the last pane in order moves focus back to the one before it.
+        let removed = panes.close_focused().unwrap();
+        assert_eq!(removed.active_editor().tab, "b");
+        assert_eq!(tabs(&panes), vec!["a"]);
+        assert_eq!(panes.focused_index(), 0);
+
+        assert!(panes.close_focused().is_none());
+        assert_eq!(tabs(&panes), vec!["a"]);
+    }
+
+    #[test]
+    fn saving_marks_other_copies_stale() {
+        let editor = |tab: &str, file_path: &str| EditorState::new(tab.into(), Some(file_path.into()), 0);
+
+        let mut panes = Panes::new(Pane::new(editor("a", "main.rs")));
+        panes.focused_mut().add_editor(editor("b", "lib.rs"));
+        panes.split_focused(SplitDirection::Vertical, Pane::new(editor("c", "main.rs")));
+
+        panes.mark_stale_copies("a", "main.rs");
+
+        let stale: Vec<(String, bool)> = panes
+            .panes()
+            .iter()
+            .flat_map(|pane| pane.editors.iter())
+            .map(|editor| (editor.tab.clone(), editor.is_stale))
+            .collect();
+        assert_eq!(
+            stale,
+            vec![("a".to_string(), false), ("b".to_string(), false), ("c".to_string(), true)]
+        );
+    }
+
+    #[test]
+    fn layout_divides_bounds_evenly() {
+        let mut panes = Panes::new(pane("a"));
+        panes.split_focused(SplitDirection::Vertical, pane("b"));
+        panes.split_focused(SplitDirection::Horizontal, pane("c"));
+
+        let bounds = LayoutRect::new(LayoutPoint::new(0.0, 0.0), LayoutSize::new(202.0, 102.0));
+        assert_eq!(
+            panes.layout(bounds),
+            vec![
+                LayoutRect::new(LayoutPoint::new(0.0, 0.0), LayoutSize::new(100.0, 102.0)),
+                LayoutRect::new(LayoutPoint::new(102.0, 0.0), LayoutSize::new(100.0, 50.0)),
+                LayoutRect::new(LayoutPoint::new(102.0, 52.0), LayoutSize::new(100.0, 50.0)),
+            ]
+        );
+    }
+}
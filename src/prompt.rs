@@ -0,0 +1,71 @@
+//! A single line of text input shown along the bottom of the window.
+//!
+//! While a prompt is open it takes all keyboard input, so typing edits the prompt's text instead
+//! of the focused editor. Enter confirms the prompt and Escape dismisses it, after which the main
+//! loop decides what to do with the text based on the prompt's [`PromptKind`][PromptKind].
+//!
+//! [PromptKind]: enum.PromptKind.html
+
+/// What the text entered in a prompt is used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptKind {
+    /// Saves the focused editor to the entered path.
+    SaveAs,
+
+    /// Moves the cursor in the focused editor to the entered `line[:column]`.
+    GoToLine,
+
+    /// Asks whether to close the window even though there are unsaved changes.
+    ConfirmClose,
+
+    /// Asks whether to close the focused tab even though it has unsaved changes.
+    ConfirmCloseTab,
+}
+
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub kind: PromptKind,
+
+    /// The text shown before the input, e.g. "Save as:".
+    pub label: &'static str,
+
+    /// The text entered so far.
+    pub text: String,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind, label: &'static str) -> Prompt {
+        Prompt {
+            kind: kind,
+            label: label,
+            text: String::new(),
+        }
+    }
+
+    /// Creates a prompt with some text already filled in.
+    pub fn with_text(kind: PromptKind, label: &'static str, text: &str) -> Prompt {
+        Prompt {
+            kind: kind,
+            label: label,
+            text: text.into(),
+        }
+    }
+
+    /// Adds a character to the end of the text.
+    pub fn insert(&mut self, character: char) {
+        self.text.push(character);
+    }
+
+    /// Removes the last character of the text, if there is one.
+    pub fn delete_backward(&mut self) {
+        self.text.pop();
+    }
+
+    /// Returns `true` if the entered text answers the prompt with "yes".
+    pub fn is_yes(&self) -> bool {
+        match self.text.trim().chars().next() {
+            Some('y') | Some('Y') => true,
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Waking up the main loop at a later time.
+//!
+//! The main loop blocks in `wait_events()` until something happens, which is normally input or a
+//! message from xi-core. Anything that needs to happen at a particular time, e.g. blinking the
+//! cursor, asks the [`Timer`][Timer] to wake the loop up at that time. The timer only wakes the
+//! loop, so it's up to the main loop to check what's due each time it runs. Periodic features do
+//! this by scheduling their next wakeup each time they run.
+//!
+//! [Timer]: struct.Timer.html
+
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Instant;
+
+/// Handle to a background thread that wakes up the main loop at requested times.
+pub struct Timer {
+    sender: Sender<Instant>,
+}
+
+impl Timer {
+    /// Starts the timer thread, which calls `wakeup` whenever a requested time is reached.
+    pub fn new<F>(wakeup: F) -> Timer
+        where F: Fn() + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel::<Instant>();
+
+        thread::spawn(move || {
+            let mut deadlines: Vec<Instant> = Vec::new();
+            loop {
+                // Wake up everything that's due, then sleep until the next deadline or until a new
+                // one is requested.
+                let now = Instant::now();
+                if deadlines.iter().any(|&deadline| deadline <= now) {
+                    deadlines.retain(|&deadline| deadline > now);
+                    wakeup();
+                }
+
+                let next_deadline = deadlines.iter().min().cloned();
+                let message = match next_deadline {
+                    Some(deadline) => receiver.recv_timeout(deadline.duration_since(now)),
+                    None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+
+                match message {
+                    Ok(deadline) => deadlines.push(deadline),
+                    Err(RecvTimeoutError::Timeout) => {}
+
+                    // The main loop has dropped the timer, so nobody needs waking up anymore.
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+        });
+
+        Timer { sender: sender }
+    }
+
+    /// Wakes up the main loop at `deadline`, or as soon as possible if it has already passed.
+    pub fn wake_at(&self, deadline: Instant) {
+        // The thread only stops once the timer is dropped, so this can't fail.
+        let _ = self.sender.send(deadline);
+    }
+}
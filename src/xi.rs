@@ -0,0 +1,921 @@
+//! Client-side types for talking to xi-core.
+//!
+//! xi-core speaks a line-delimited JSON-RPC protocol over stdin/stdout. Rather than formatting JSON
+//! by hand, every message is built from the serde-derived types in this module and written out by
+//! [`XiClient`][XiClient], so text containing quotes, backslashes, or control characters is always
+//! escaped correctly.
+//!
+//! Messages coming back from xi-core are either responses to a request we made, which are routed
+//! to whoever is waiting on that request's id, or unsolicited notifications (e.g. `update`), which
+//! are decoded into a [`Notification`][Notification] and forwarded to the main thread over a
+//! channel. Messages that can't be decoded (e.g. ones added in a newer version of xi-core) are
+//! logged and skipped rather than bringing down the editor.
+//!
+//! [XiClient]: struct.XiClient.html
+//! [Notification]: enum.Notification.html
+
+use serde::Serialize;
+use serde_json::{self, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io::{BufRead, Write};
+use std::process::ChildStdin;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// Bit flag set in the font style of a style span for bold text.
+const FONT_STYLE_BOLD: u64 = 1;
+
+/// Bit flag set in the font style of a style span for italic text.
+const FONT_STYLE_ITALIC: u64 = 4;
+
+/// Bit flag set in the flags of a `click` to extend the selection to the clicked position, as when
+/// Shift is held.
+const CLICK_FLAG_EXTEND_SELECTION: u64 = 2;
+
+/// The result of a request, either the `result` or the `error` member of xi-core's response.
+pub type Response = Result<Value, Value>;
+
+/// A command that can be sent to a tab in xi-core as part of an `edit` notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditCommand<'a> {
+    /// Inserts the text at each cursor, replacing any selected text.
+    Insert(&'a str),
+
+    /// Loads the file at the given path into the tab.
+    Open(&'a str),
+
+    /// Notifies xi-core of the range of lines currently visible, so that it knows which lines
+    /// to send in `update` notifications.
+    ///
+    /// The range is given as `(first, last)`, where `last` is exclusive.
+    Scroll(usize, usize),
+
+    /// Places the cursor at a position in the document, as when clicking with the mouse.
+    ///
+    /// `click_count` is the number of clicks in quick succession, so that a double click selects a
+    /// word and a triple click selects a line.
+    Click {
+        line: usize,
+        col: usize,
+        extend_selection: bool,
+        click_count: usize,
+    },
+
+    /// Extends the selection started by a `Click` to a position, as when dragging the mouse.
+    Drag {
+        line: usize,
+        col: usize,
+    },
+
+    InsertNewline,
+    DeleteBackward,
+    DeleteForward,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    MoveLeftAndModifySelection,
+    MoveRightAndModifySelection,
+    MoveUpAndModifySelection,
+    MoveDownAndModifySelection,
+    MoveToLeftEndOfLine,
+    MoveToRightEndOfLine,
+    MoveToLeftEndOfLineAndModifySelection,
+    MoveToRightEndOfLineAndModifySelection,
+    MoveWordLeft,
+    MoveWordRight,
+    MoveWordLeftAndModifySelection,
+    MoveWordRightAndModifySelection,
+    MoveToBeginningOfDocument,
+    MoveToEndOfDocument,
+    MoveToBeginningOfDocumentAndModifySelection,
+    MoveToEndOfDocumentAndModifySelection,
+
+    /// Moves the cursor up by the height of the visible range, see `Scroll`.
+    ScrollPageUp,
+
+    /// Moves the cursor down by the height of the visible range, see `Scroll`.
+    ScrollPageDown,
+
+    PageUpAndModifySelection,
+    PageDownAndModifySelection,
+    SelectAll,
+    Undo,
+    Redo,
+
+    /// Searches the document for some text, highlighting every match. Searching for an empty
+    /// string clears the highlights.
+    Find {
+        chars: &'a str,
+        case_sensitive: bool,
+
+        /// Whether `chars` is a regular expression rather than plain text.
+        regex: bool,
+
+        /// Whether to only match whole words.
+        whole_words: bool,
+    },
+
+    /// Selects the next match of the current search, wrapping around at the end of the document.
+    FindNext,
+
+    /// Selects the previous match of the current search, wrapping around at the start of the
+    /// document.
+    FindPrevious,
+
+    /// Sets the text that matches of the current search are replaced with.
+    Replace(&'a str),
+
+    /// Replaces the selected match, or the next one if no match is selected.
+    ReplaceNext,
+
+    /// Replaces every match of the current search.
+    ReplaceAll,
+}
+
+impl<'a> EditCommand<'a> {
+    /// Returns `true` if the command can change the contents of the document.
+    ///
+    /// Undoing and redoing only change the document if there's history for them to act on.
+    pub fn modifies_buffer(&self) -> bool {
+        match *self {
+            EditCommand::Insert(_)
+            | EditCommand::InsertNewline
+            | EditCommand::DeleteBackward
+            | EditCommand::DeleteForward
+            | EditCommand::Undo
+            | EditCommand::Redo
+            | EditCommand::ReplaceNext
+            | EditCommand::ReplaceAll => true,
+            _ => false,
+        }
+    }
+}
+
+/// An unsolicited message from xi-core.
+#[derive(Debug, Clone)]
+pub enum Notification {
+    /// New contents for a tab.
+    Update(Update),
+}
+
+/// The contents of an `update` notification.
+///
+/// Every part of the update other than the tab is optional, and only the parts that have changed
+/// are sent.
+#[derive(Debug, Clone)]
+pub struct Update {
+    /// The name of the tab being updated.
+    pub tab: String,
+
+    /// The total number of lines in the document.
+    pub height: Option<usize>,
+
+    /// The index of the first line in `lines`.
+    pub first_line: Option<usize>,
+
+    /// The lines currently visible in the view.
+    pub lines: Option<Vec<Line>>,
+
+    /// The `(line, column)` of a position that should be scrolled into view, usually the cursor.
+    pub scroll_to: Option<(usize, usize)>,
+}
+
+/// A single line of text from an `update`, along with its line controls.
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub text: String,
+
+    /// The columns of any cursors in the line.
+    pub cursors: Vec<usize>,
+
+    /// The `(start, end)` columns of any selections in the line.
+    pub selections: Vec<(usize, usize)>,
+
+    /// The `(start, end)` columns of any matches of the current search in the line.
+    pub find_matches: Vec<(usize, usize)>,
+
+    /// Styles applied to ranges of the line, e.g. by syntax highlighting.
+    pub styles: Vec<Style>,
+}
+
+/// A style applied to the range of columns `start..end` in a line.
+#[derive(Debug, Clone, Copy)]
+pub struct Style {
+    pub start: usize,
+    pub end: usize,
+
+    /// The foreground color packed as `0xAARRGGBB`.
+    pub argb: u32,
+
+    /// Bit flags for font styles such as bold and italic.
+    pub font_style: u64,
+}
+
+impl Style {
+    pub fn is_bold(&self) -> bool {
+        self.font_style & FONT_STYLE_BOLD != 0
+    }
+
+    pub fn is_italic(&self) -> bool {
+        self.font_style & FONT_STYLE_ITALIC != 0
+    }
+}
+
+/// An error that occurred while decoding a message from xi-core.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The message wasn't valid JSON, or wasn't a valid JSON-RPC message.
+    Json(serde_json::Error),
+
+    /// A field required by the message wasn't present.
+    MissingField(&'static str),
+
+    /// A field in the message didn't have the expected type.
+    InvalidType {
+        field: &'static str,
+        expected: &'static str,
+    },
+
+    /// A notification with a method we don't know how to handle.
+    UnknownMethod(String),
+
+    /// A line control with a type we don't know how to handle.
+    UnknownControl(String),
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::Json(ref error) => write!(formatter, "Invalid message: {}", error),
+            DecodeError::MissingField(field) => write!(formatter, "Missing field {:?}", field),
+            DecodeError::InvalidType { field, expected } => {
+                write!(formatter, "Expected {:?} to be {}", field, expected)
+            }
+            DecodeError::UnknownMethod(ref method) => write!(formatter, "Unknown method {:?}", method),
+            DecodeError::UnknownControl(ref control) => {
+                write!(formatter, "Unknown line control {:?}", control)
+            }
+        }
+    }
+}
+
+impl Error for DecodeError {
+    fn description(&self) -> &str {
+        match *self {
+            DecodeError::Json(_) => "invalid message",
+            DecodeError::MissingField(_) => "missing field",
+            DecodeError::InvalidType { .. } => "field had invalid type",
+            DecodeError::UnknownMethod(_) => "unknown method",
+            DecodeError::UnknownControl(_) => "unknown line control",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            DecodeError::Json(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for DecodeError {
+    fn from(error: serde_json::Error) -> DecodeError {
+        DecodeError::Json(error)
+    }
+}
+
+/// The connection to a running xi-core process.
+///
+/// Messages are written to `W`, which is the stdin pipe of the xi-core process except in tests.
+pub struct XiClient<W = ChildStdin> {
+    stdin: W,
+
+    /// The id to use for the next request.
+    next_id: u64,
+
+    /// Requests that haven't received a response yet, keyed by request id.
+    ///
+    /// This is shared with the reader thread, which removes the entry for each response as it
+    /// arrives and sends the result to the waiting receiver. Once xi-core disconnects the reader
+    /// thread sets this to `None`, so that nobody is left waiting on a response that's never coming.
+    pending: Arc<Mutex<Option<HashMap<u64, Sender<Response>>>>>,
+}
+
+impl<W: Write> XiClient<W> {
+    /// Creates a client from the stdin and stdout pipes of the xi-core process.
+    ///
+    /// This spawns a thread that reads messages from `stdout`. Notifications are sent over the
+    /// returned channel, and `wakeup` is called after each message is handled so that the main
+    /// thread knows to check for new notifications and responses.
+    ///
+    /// If xi-core disconnects, the receivers for any requests still waiting on a response are
+    /// disconnected as well, as are the receivers for any requests made afterwards.
+    pub fn new<R, F>(stdin: W, stdout: R, wakeup: F) -> (XiClient<W>, Receiver<Notification>)
+        where R: BufRead + Send + 'static,
+              F: Fn() + Send + 'static,
+    {
+        let pending = Arc::new(Mutex::new(Some(HashMap::<u64, Sender<Response>>::new())));
+        let (sender, receiver) = mpsc::channel();
+
+        let reader_pending = pending.clone();
+        thread::spawn(move || {
+            for line in stdout.lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(error) => {
+                        eprintln!("Error receiving message from xi-core, disconnecting: {}", error);
+                        break;
+                    }
+                };
+
+                let message = match serde_json::from_str::<IncomingMessage>(&*line) {
+                    Ok(message) => message,
+                    Err(error) => {
+                        eprintln!("Ignoring message from xi-core: {}\n{}", DecodeError::from(error), line);
+                        continue;
+                    }
+                };
+
+                // Anything with a method is a notification, anything else is a response to one of
+                // our requests.
+                if let Some(method) = message.method {
+                    let params = message.params.unwrap_or(Value::Null);
+                    let notification = match decode_notification(&*method, &params) {
+                        Ok(notification) => notification,
+                        Err(error) => {
+                            eprintln!("Ignoring notification from xi-core: {}\n{}", error, line);
+                            continue;
+                        }
+                    };
+
+                    // The main thread has hung up, so there's nobody left to read messages.
+                    if sender.send(notification).is_err() {
+                        break;
+                    }
+                } else if let Some(id) = message.id {
+                    let response = match message.error {
+                        Some(error) => Err(error),
+                        None => Ok(message.result.unwrap_or(Value::Null)),
+                    };
+
+                    // It's fine if nobody is waiting on the response anymore, in which case it's
+                    // simply dropped.
+                    let waiting = reader_pending.lock()
+                        .unwrap()
+                        .as_mut()
+                        .and_then(|pending| pending.remove(&id));
+                    if let Some(waiting) = waiting {
+                        let _ = waiting.send(response);
+                    }
+                }
+
+                wakeup();
+            }
+
+            // xi-core has gone away, so drop the senders for any requests still waiting on it. This
+            // disconnects their receivers, letting whoever is waiting know that no response is
+            // coming.
+            *reader_pending.lock().unwrap() = None;
+            wakeup();
+        });
+
+        let client = XiClient {
+            stdin: stdin,
+            next_id: 0,
+            pending: pending,
+        };
+
+        (client, receiver)
+    }
+
+    /// Asks xi-core to open a new tab.
+    ///
+    /// The response contains the name of the new tab, which is used to identify it in all
+    /// future messages.
+    pub fn new_tab(&mut self) -> Receiver<Response> {
+        let params: [(); 0] = [];
+        self.request("new_tab", params)
+    }
+
+    /// Tells xi-core to close a tab. No more updates will be sent for the tab.
+    pub fn delete_tab(&mut self, tab: &str) {
+        self.send(&NotificationMessage {
+            method: "delete_tab",
+            params: TabParams { tab: tab },
+        });
+    }
+
+    /// Asks xi-core to write the contents of a tab to `filename`.
+    ///
+    /// This is sent as a request rather than a plain edit command so that the response can be used
+    /// to tell when the file has been written.
+    pub fn save(&mut self, tab: &str, filename: &str) -> Receiver<Response> {
+        self.request("edit", EditParams {
+            method: "save",
+            params: FilenameParams { filename: filename },
+            tab: tab,
+        })
+    }
+
+    /// Asks xi-core for the selected text in a tab, e.g. to put it on the clipboard.
+    ///
+    /// The response contains the selected text, or `null` if nothing is selected.
+    pub fn copy(&mut self, tab: &str) -> Receiver<Response> {
+        self.request("edit", EditParams {
+            method: "copy",
+            params: EmptyParams {},
+            tab: tab,
+        })
+    }
+
+    /// Asks xi-core to remove the selected text in a tab. The response contains the removed text,
+    /// the same as for `copy()`.
+    pub fn cut(&mut self, tab: &str) -> Receiver<Response> {
+        self.request("edit", EditParams {
+            method: "cut",
+            params: EmptyParams {},
+            tab: tab,
+        })
+    }
+
+    /// Sends an edit command to the specified tab.
+    pub fn edit(&mut self, tab: &str, command: EditCommand) {
+        match command {
+            EditCommand::Insert(chars) => self.send_edit(tab, "insert", InsertParams { chars: chars }),
+            EditCommand::Open(filename) => self.send_edit(tab, "open", FilenameParams { filename: filename }),
+            EditCommand::Scroll(first, last) => self.send_edit(tab, "scroll", (first, last)),
+            EditCommand::Click { line, col, extend_selection, click_count } => {
+                let flags = if extend_selection { CLICK_FLAG_EXTEND_SELECTION } else { 0 };
+                self.send_edit(tab, "click", (line, col, flags, click_count))
+            }
+            EditCommand::Drag { line, col } => self.send_edit(tab, "drag", (line, col, 0)),
+            EditCommand::InsertNewline => self.send_edit(tab, "insert_newline", EmptyParams {}),
+            EditCommand::DeleteBackward => self.send_edit(tab, "delete_backward", EmptyParams {}),
+            EditCommand::DeleteForward => self.send_edit(tab, "delete_forward", EmptyParams {}),
+            EditCommand::MoveLeft => self.send_edit(tab, "move_left", EmptyParams {}),
+            EditCommand::MoveRight => self.send_edit(tab, "move_right", EmptyParams {}),
+            EditCommand::MoveUp => self.send_edit(tab, "move_up", EmptyParams {}),
+            EditCommand::MoveDown => self.send_edit(tab, "move_down", EmptyParams {}),
+            EditCommand::MoveLeftAndModifySelection => self.send_edit(tab, "move_left_and_modify_selection", EmptyParams {}),
+            EditCommand::MoveRightAndModifySelection => self.send_edit(tab, "move_right_and_modify_selection", EmptyParams {}),
+            EditCommand::MoveUpAndModifySelection => self.send_edit(tab, "move_up_and_modify_selection", EmptyParams {}),
+            EditCommand::MoveDownAndModifySelection => self.send_edit(tab, "move_down_and_modify_selection", EmptyParams {}),
+            EditCommand::MoveToLeftEndOfLine => self.send_edit(tab, "move_to_left_end_of_line", EmptyParams {}),
+            EditCommand::MoveToRightEndOfLine => self.send_edit(tab, "move_to_right_end_of_line", EmptyParams {}),
+            EditCommand::MoveToLeftEndOfLineAndModifySelection => self.send_edit(tab, "move_to_left_end_of_line_and_modify_selection", EmptyParams {}),
+            EditCommand::MoveToRightEndOfLineAndModifySelection => self.send_edit(tab, "move_to_right_end_of_line_and_modify_selection", EmptyParams {}),
+            EditCommand::MoveWordLeft => self.send_edit(tab, "move_word_left", EmptyParams {}),
+            EditCommand::MoveWordRight => self.send_edit(tab, "move_word_right", EmptyParams {}),
+            EditCommand::MoveWordLeftAndModifySelection => self.send_edit(tab, "move_word_left_and_modify_selection", EmptyParams {}),
+            EditCommand::MoveWordRightAndModifySelection => self.send_edit(tab, "move_word_right_and_modify_selection", EmptyParams {}),
+            EditCommand::MoveToBeginningOfDocument => self.send_edit(tab, "move_to_beginning_of_document", EmptyParams {}),
+            EditCommand::MoveToEndOfDocument => self.send_edit(tab, "move_to_end_of_document", EmptyParams {}),
+            EditCommand::MoveToBeginningOfDocumentAndModifySelection => self.send_edit(tab, "move_to_beginning_of_document_and_modify_selection", EmptyParams {}),
+            EditCommand::MoveToEndOfDocumentAndModifySelection => self.send_edit(tab, "move_to_end_of_document_and_modify_selection", EmptyParams {}),
+            EditCommand::ScrollPageUp => self.send_edit(tab, "scroll_page_up", EmptyParams {}),
+            EditCommand::ScrollPageDown => self.send_edit(tab, "scroll_page_down", EmptyParams {}),
+            EditCommand::PageUpAndModifySelection => self.send_edit(tab, "page_up_and_modify_selection", EmptyParams {}),
+            EditCommand::PageDownAndModifySelection => self.send_edit(tab, "page_down_and_modify_selection", EmptyParams {}),
+            EditCommand::SelectAll => self.send_edit(tab, "select_all", EmptyParams {}),
+            EditCommand::Undo => self.send_edit(tab, "undo", EmptyParams {}),
+            EditCommand::Redo => self.send_edit(tab, "redo", EmptyParams {}),
+            EditCommand::Find { chars, case_sensitive, regex, whole_words } => {
+                self.send_edit(tab, "find", FindParams {
+                    chars: if chars.is_empty() { None } else { Some(chars) },
+                    case_sensitive: case_sensitive,
+                    regex: regex,
+                    whole_words: whole_words,
+                })
+            }
+            EditCommand::FindNext => self.send_edit(tab, "find_next", FindNextParams { wrap_around: true, allow_same: false }),
+            EditCommand::FindPrevious => self.send_edit(tab, "find_previous", FindNextParams { wrap_around: true, allow_same: false }),
+            EditCommand::Replace(chars) => self.send_edit(tab, "replace", ReplaceParams { chars: chars, preserve_case: false }),
+            EditCommand::ReplaceNext => self.send_edit(tab, "replace_next", EmptyParams {}),
+            EditCommand::ReplaceAll => self.send_edit(tab, "replace_all", EmptyParams {}),
+        }
+    }
+
+    /// Sends a request to xi-core, returning a channel that will receive the response.
+    fn request<P: Serialize>(&mut self, method: &str, params: P) -> Receiver<Response> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        // Register the request before sending it so that the reader thread can't receive the
+        // response before we're ready for it. If xi-core has already disconnected the sender is
+        // dropped, so the receiver is disconnected straight away.
+        let (sender, receiver) = mpsc::channel();
+        if let Some(ref mut pending) = *self.pending.lock().unwrap() {
+            pending.insert(id, sender);
+        }
+
+        self.send(&RequestMessage {
+            id: id,
+            method: method,
+            params: params,
+        });
+
+        receiver
+    }
+
+    fn send_edit<P: Serialize>(&mut self, tab: &str, method: &str, params: P) {
+        self.send(&NotificationMessage {
+            method: "edit",
+            params: EditParams {
+                method: method,
+                params: params,
+                tab: tab,
+            },
+        });
+    }
+
+    /// Serializes a message and writes it to xi-core as a single line.
+    ///
+    /// Writing fails if xi-core has exited, in which case the message is dropped. The reader thread
+    /// notices the disconnect separately, see `new()`.
+    fn send<T: Serialize>(&mut self, message: &T) {
+        let mut line = serde_json::to_vec(message).expect("Failed to serialize message to xi-core");
+        line.push(b'\n');
+        if let Err(error) = self.stdin.write_all(&*line) {
+            eprintln!("Failed to send message to xi-core: {}", error);
+        }
+    }
+}
+
+/// A message from xi-core, which is either a response or a notification.
+#[derive(Debug, Deserialize)]
+struct IncomingMessage {
+    id: Option<u64>,
+    method: Option<String>,
+    params: Option<Value>,
+    result: Option<Value>,
+    error: Option<Value>,
+}
+
+/// A message to xi-core that expects a response with a matching `id`.
+#[derive(Debug, Serialize)]
+struct RequestMessage<'a, P> {
+    id: u64,
+    method: &'a str,
+    params: P,
+}
+
+/// A message to xi-core that doesn't expect a response.
+#[derive(Debug, Serialize)]
+struct NotificationMessage<'a, P> {
+    method: &'a str,
+    params: P,
+}
+
+/// The params of an `edit` notification, which wraps a command for a specific tab.
+#[derive(Debug, Serialize)]
+struct EditParams<'a, P> {
+    method: &'a str,
+    params: P,
+    tab: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct TabParams<'a> {
+    tab: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct InsertParams<'a> {
+    chars: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct FilenameParams<'a> {
+    filename: &'a str,
+}
+
+/// The params of a `find` command, where `chars` is `None` to clear the search.
+#[derive(Debug, Serialize)]
+struct FindParams<'a> {
+    chars: Option<&'a str>,
+    case_sensitive: bool,
+    regex: bool,
+    whole_words: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct FindNextParams {
+    wrap_around: bool,
+    allow_same: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ReplaceParams<'a> {
+    chars: &'a str,
+    preserve_case: bool,
+}
+
+/// Params for commands that don't take any arguments, serialized as `{}`.
+#[derive(Debug, Serialize)]
+struct EmptyParams {}
+
+fn decode_notification(method: &str, params: &Value) -> Result<Notification, DecodeError> {
+    match method {
+        "update" => decode_update(params).map(Notification::Update),
+        _ => Err(DecodeError::UnknownMethod(method.into())),
+    }
+}
+
+/// Decodes an `update` notification.
+///
+/// Only the tab is required. Other parts of the update that can't be decoded are logged and
+/// skipped, the same as line controls, so that the rest of the update is still applied.
+fn decode_update(params: &Value) -> Result<Update, DecodeError> {
+    let tab = as_str(required_field(params, "tab")?, "tab")?;
+    let update = required_field(params, "update")?;
+
+    let height = decode_optional_field(update, "height", |height| as_usize(height, "height"));
+    let first_line = decode_optional_field(update, "first_line", |first_line| as_usize(first_line, "first_line"));
+
+    let lines = decode_optional_field(update, "lines", |lines| {
+        let lines = as_array(lines, "lines")?;
+        let mut decoded = Vec::with_capacity(lines.len());
+        for line in lines {
+            decoded.push(decode_line(line)?);
+        }
+        Ok(decoded)
+    });
+
+    let scroll_to = decode_optional_field(update, "scrollto", |scroll_to| {
+        let scroll_to = as_array(scroll_to, "scrollto")?;
+        let line = as_usize(element(scroll_to, 0, "scrollto line")?, "scrollto line")?;
+        let col = as_usize(element(scroll_to, 1, "scrollto column")?, "scrollto column")?;
+        Ok((line, col))
+    });
+
+    Ok(Update {
+        tab: tab.into(),
+        height: height,
+        first_line: first_line,
+        lines: lines,
+        scroll_to: scroll_to,
+    })
+}
+
+/// Decodes a line, which is an array containing the text followed by any line controls.
+///
+/// Line controls that can't be decoded are logged and skipped so that the rest of the line can
+/// still be displayed.
+fn decode_line(line: &Value) -> Result<Line, DecodeError> {
+    let line = as_array(line, "line")?;
+
+    // TODO: If we're doing visible whitespace we don't want to trim the trailing whitespace.
+    // TODO: We probably want to perform unicode normalization here? Or maybe we want to do it when
+    // we generate the glyphs?
+    let text = as_str(element(line, 0, "line text")?, "line text")?;
+
+    let mut decoded = Line {
+        text: text.into(),
+        cursors: Vec::new(),
+        selections: Vec::new(),
+        find_matches: Vec::new(),
+        styles: Vec::new(),
+    };
+
+    for control in &line[1..] {
+        if let Err(error) = decode_line_control(control, &mut decoded) {
+            eprintln!("Ignoring line control from xi-core: {}\n{}", error, control);
+        }
+    }
+
+    Ok(decoded)
+}
+
+/// Decodes a single line control, adding it to `line`.
+///
+/// Xi internally represents columns as a `usize`, so the casts from the JSON representation
+/// shouldn't overflow.
+fn decode_line_control(control: &Value, line: &mut Line) -> Result<(), DecodeError> {
+    let control = as_array(control, "line control")?;
+    let control_type = as_str(element(control, 0, "line control type")?, "line control type")?;
+
+    match control_type {
+        "cursor" => {
+            let col = as_usize(element(control, 1, "cursor column")?, "cursor column")?;
+            line.cursors.push(col);
+        }
+
+        "sel" => {
+            let start = as_usize(element(control, 1, "selection start")?, "selection start")?;
+            let end = as_usize(element(control, 2, "selection end")?, "selection end")?;
+            line.selections.push((start, end));
+        }
+
+        "find" => {
+            let start = as_usize(element(control, 1, "match start")?, "match start")?;
+            let end = as_usize(element(control, 2, "match end")?, "match end")?;
+            line.find_matches.push((start, end));
+        }
+
+        "fg" => {
+            let start = as_usize(element(control, 1, "style start")?, "style start")?;
+            let end = as_usize(element(control, 2, "style end")?, "style end")?;
+            let argb = element(control, 3, "style color")?
+                .as_u64()
+                .ok_or(DecodeError::InvalidType { field: "style color", expected: "an integer" })?;
+
+            // The font style was added to the protocol later on, so treat it as optional.
+            let font_style = match control.get(4) {
+                Some(font_style) => {
+                    font_style.as_u64()
+                        .ok_or(DecodeError::InvalidType { field: "font style", expected: "an integer" })?
+                }
+                None => 0,
+            };
+
+            line.styles.push(Style {
+                start: start,
+                end: end,
+                argb: argb as u32,
+                font_style: font_style,
+            });
+        }
+
+        _ => return Err(DecodeError::UnknownControl(control_type.into())),
+    }
+
+    Ok(())
+}
+
+/// Decodes an optional field of an object with `decode`, logging the error and treating the field as
+/// missing if it can't be decoded.
+fn decode_optional_field<'a, T, F>(value: &'a Value, field: &'static str, decode: F) -> Option<T>
+    where F: FnOnce(&'a Value) -> Result<T, DecodeError>,
+{
+    let field_value = match optional_field(value, field) {
+        Some(field_value) => field_value,
+        None => return None,
+    };
+
+    match decode(field_value) {
+        Ok(decoded) => Some(decoded),
+        Err(error) => {
+            eprintln!("Ignoring {:?} from xi-core: {}\n{}", field, error, field_value);
+            None
+        }
+    }
+}
+
+/// Gets a field of an object, treating `null` the same as a missing field.
+fn optional_field<'a>(value: &'a Value, field: &'static str) -> Option<&'a Value> {
+    match value.get(field) {
+        Some(&Value::Null) | None => None,
+        Some(field) => Some(field),
+    }
+}
+
+fn required_field<'a>(value: &'a Value, field: &'static str) -> Result<&'a Value, DecodeError> {
+    optional_field(value, field).ok_or(DecodeError::MissingField(field))
+}
+
+fn element<'a>(values: &'a [Value], index: usize, field: &'static str) -> Result<&'a Value, DecodeError> {
+    values.get(index).ok_or(DecodeError::MissingField(field))
+}
+
+fn as_str<'a>(value: &'a Value, field: &'static str) -> Result<&'a str, DecodeError> {
+    value.as_str().ok_or(DecodeError::InvalidType { field: field, expected: "a string" })
+}
+
+fn as_usize(value: &Value, field: &'static str) -> Result<usize, DecodeError> {
+    value.as_u64()
+        .map(|value| value as usize)
+        .ok_or(DecodeError::InvalidType { field: field, expected: "an integer" })
+}
+
+fn as_array<'a>(value: &'a Value, field: &'static str) -> Result<&'a [Value], DecodeError> {
+    value.as_array()
+        .map(|values| &**values)
+        .ok_or(DecodeError::InvalidType { field: field, expected: "an array" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    /// Creates a client that writes its messages to a buffer, with nothing to read from xi-core.
+    fn client() -> XiClient<Vec<u8>> {
+        XiClient::new(Vec::new(), io::empty(), || {}).0
+    }
+
+    /// Returns the lines written by the client so far.
+    fn sent(client: &XiClient<Vec<u8>>) -> Vec<String> {
+        String::from_utf8(client.stdin.clone()).unwrap().lines().map(String::from).collect()
+    }
+
+    fn decode(method: &str, params: &str) -> Result<Notification, DecodeError> {
+        decode_notification(method, &serde_json::from_str(params).unwrap())
+    }
+
+    fn decode_update_params(params: &str) -> Update {
+        match decode("update", params) {
+            Ok(Notification::Update(update)) => update,
+            other => panic!("Expected an update, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decode_unknown_method() {
+        match decode("plugin_started", "{}") {
+            Err(DecodeError::UnknownMethod(ref method)) if method == "plugin_started" => {}
+            other => panic!("Expected an unknown method error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decode_update_without_tab() {
+        match decode("update", r#"{"update": {}}"#) {
+            Err(DecodeError::MissingField("tab")) => {}
+            other => panic!("Expected a missing field error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decode_update_skips_unknown_controls() {
+        let update = decode_update_params(r#"{"tab": "1", "update": {"lines": [["abc", ["squiggle", 0, 1], ["cursor", 2]]]}}"#);
+        let lines = update.lines.unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].text, "abc");
+        assert_eq!(lines[0].cursors, vec![2]);
+    }
+
+    #[test]
+    fn decode_update_accepts_style_without_font_style() {
+        let update = decode_update_params(r#"{"tab": "1", "update": {"lines": [["abc", ["fg", 0, 3, 4294901760]]]}}"#);
+        let style = update.lines.unwrap()[0].styles[0];
+        assert_eq!((style.start, style.end, style.argb, style.font_style), (0, 3, 0xffff0000, 0));
+    }
+
+    #[test]
+    fn decode_update_skips_invalid_optional_fields() {
+        let update = decode_update_params(
+            r#"{"tab": "1", "update": {"height": "many", "scrollto": {"line": 1}, "lines": [["abc"]]}}"#,
+        );
+        assert_eq!(update.tab, "1");
+        assert!(update.height.is_none());
+        assert!(update.scroll_to.is_none());
+        assert_eq!(update.lines.unwrap()[0].text, "abc");
+    }
+
+    #[test]
+    fn insert_escapes_text() {
+        let mut client = client();
+        client.edit("tab", EditCommand::Insert("\"\\\n"));
+        assert_eq!(
+            sent(&client),
+            vec![r#"{"method":"edit","params":{"method":"insert","params":{"chars":"\"\\\n"},"tab":"tab"}}"#]
+        );
+    }
+
+    #[test]
+    fn scroll_and_click_send_arrays() {
+        let mut client = client();
+        client.edit("tab", EditCommand::Scroll(0, 10));
+        client.edit("tab", EditCommand::Click {
+            line: 3,
+            col: 4,
+            extend_selection: true,
+            click_count: 1,
+        });
+        assert_eq!(
+            sent(&client),
+            vec![
+                r#"{"method":"edit","params":{"method":"scroll","params":[0,10],"tab":"tab"}}"#,
+                r#"{"method":"edit","params":{"method":"click","params":[3,4,2,1],"tab":"tab"}}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn commands_without_arguments_send_empty_object() {
+        let mut client = client();
+        client.edit("tab", EditCommand::Undo);
+        assert_eq!(
+            sent(&client),
+            vec![r#"{"method":"edit","params":{"method":"undo","params":{},"tab":"tab"}}"#]
+        );
+    }
+
+    #[test]
+    fn requests_have_increasing_ids() {
+        let mut client = client();
+        client.new_tab();
+        client.save("tab", "a.txt");
+        assert_eq!(
+            sent(&client),
+            vec![
+                r#"{"id":0,"method":"new_tab","params":[]}"#,
+                r#"{"id":1,"method":"edit","params":{"method":"save","params":{"filename":"a.txt"},"tab":"tab"}}"#,
+            ]
+        );
+    }
+}